@@ -0,0 +1,220 @@
+use anyhow::{anyhow, bail};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+// athlete parameters that can be set in the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum AthleteKey {
+    // maximum heart rate in bpm
+    MaxHr,
+    // resting heart rate in bpm
+    RestingHr,
+    // lactate threshold heart rate in bpm
+    Lthr,
+    // functional threshold power in W
+    Ftp,
+    // running threshold pace in seconds per km
+    ThresholdPace,
+    // body weight in kg
+    Weight,
+    // heart rate zone boundaries in bpm, ascending
+    HrZones,
+    // power zone boundaries in W, ascending
+    PowerZones,
+    // pace zone boundaries in seconds per km, from slowest to fastest
+    PaceZones,
+}
+
+impl AthleteKey {
+    pub const ALL: [AthleteKey; 9] = [
+        AthleteKey::MaxHr,
+        AthleteKey::RestingHr,
+        AthleteKey::Lthr,
+        AthleteKey::Ftp,
+        AthleteKey::ThresholdPace,
+        AthleteKey::Weight,
+        AthleteKey::HrZones,
+        AthleteKey::PowerZones,
+        AthleteKey::PaceZones,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AthleteKey::MaxHr => "max_hr",
+            AthleteKey::RestingHr => "resting_hr",
+            AthleteKey::Lthr => "lthr",
+            AthleteKey::Ftp => "ftp",
+            AthleteKey::ThresholdPace => "threshold_pace",
+            AthleteKey::Weight => "weight",
+            AthleteKey::HrZones => "hr_zones",
+            AthleteKey::PowerZones => "power_zones",
+            AthleteKey::PaceZones => "pace_zones",
+        }
+    }
+
+    fn is_pace(&self) -> bool {
+        matches!(self, AthleteKey::ThresholdPace | AthleteKey::PaceZones)
+    }
+}
+
+// one `[[athlete]]` entry of the config; values apply from `since` until a later entry sets them
+// again, and an entry without `since` applies to all dates
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Athlete {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<NaiveDate>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_hr: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resting_hr: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lthr: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ftp: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold_pace: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hr_zones: Option<Vec<f64>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub power_zones: Option<Vec<f64>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pace_zones: Option<Vec<f64>>,
+}
+
+impl Athlete {
+    // profile effective on `date`, each value taken from the latest entry that sets it
+    pub fn effective(entries: &[Athlete], date: NaiveDate) -> Athlete {
+        let mut applicable: Vec<&Athlete> = entries
+            .iter()
+            .filter(|entry| entry.since.is_none_or(|since| since <= date))
+            .collect();
+        applicable.sort_by_key(|entry| entry.since);
+
+        let mut profile = Athlete::default();
+        for entry in applicable {
+            profile.overlay(entry);
+        }
+        profile
+    }
+
+    fn overlay(&mut self, other: &Athlete) {
+        for key in AthleteKey::ALL {
+            if let Some(values) = other.values(key) {
+                self.set_values(key, values);
+            }
+        }
+        self.since = other.since.or(self.since);
+    }
+
+    // every value is stored as a list so scalar and zone keys share get/set
+    fn values(&self, key: AthleteKey) -> Option<Vec<f64>> {
+        match key {
+            AthleteKey::MaxHr => self.max_hr.map(|v| vec![v]),
+            AthleteKey::RestingHr => self.resting_hr.map(|v| vec![v]),
+            AthleteKey::Lthr => self.lthr.map(|v| vec![v]),
+            AthleteKey::Ftp => self.ftp.map(|v| vec![v]),
+            AthleteKey::ThresholdPace => self.threshold_pace.map(|v| vec![v]),
+            AthleteKey::Weight => self.weight.map(|v| vec![v]),
+            AthleteKey::HrZones => self.hr_zones.clone(),
+            AthleteKey::PowerZones => self.power_zones.clone(),
+            AthleteKey::PaceZones => self.pace_zones.clone(),
+        }
+    }
+
+    fn set_values(&mut self, key: AthleteKey, values: Vec<f64>) {
+        let first = values.first().copied();
+        match key {
+            AthleteKey::MaxHr => self.max_hr = first,
+            AthleteKey::RestingHr => self.resting_hr = first,
+            AthleteKey::Lthr => self.lthr = first,
+            AthleteKey::Ftp => self.ftp = first,
+            AthleteKey::ThresholdPace => self.threshold_pace = first,
+            AthleteKey::Weight => self.weight = first,
+            AthleteKey::HrZones => self.hr_zones = Some(values),
+            AthleteKey::PowerZones => self.power_zones = Some(values),
+            AthleteKey::PaceZones => self.pace_zones = Some(values),
+        }
+    }
+
+    // value formatted the way it is entered with `config set`
+    pub fn get(&self, key: AthleteKey) -> Option<String> {
+        let values = self.values(key)?;
+        let formatted = values
+            .iter()
+            .map(|v| {
+                if key.is_pace() {
+                    format_pace(*v)
+                } else {
+                    format!("{}", v)
+                }
+            })
+            .collect::<Vec<_>>();
+        Some(formatted.join(","))
+    }
+
+    // parse and validate a value entered with `config set`
+    pub fn set(&mut self, key: AthleteKey, value: &str) -> anyhow::Result<()> {
+        let values = value
+            .split(',')
+            .map(|v| {
+                let v = v.trim();
+                let parsed = if key.is_pace() {
+                    parse_pace(v)
+                } else {
+                    v.parse::<f64>().ok()
+                };
+                parsed
+                    .filter(|p| p.is_finite() && *p > 0.0)
+                    .ok_or_else(|| anyhow!("Invalid {} value '{}'", key.name(), v))
+            })
+            .collect::<anyhow::Result<Vec<f64>>>()?;
+
+        match key {
+            AthleteKey::HrZones | AthleteKey::PowerZones
+                if !values.windows(2).all(|pair| pair[0] < pair[1]) =>
+            {
+                bail!("{} boundaries must be in ascending order", key.name())
+            }
+            AthleteKey::PaceZones if !values.windows(2).all(|pair| pair[0] > pair[1]) => {
+                bail!("pace_zones boundaries must go from slowest to fastest pace")
+            }
+            AthleteKey::HrZones | AthleteKey::PowerZones | AthleteKey::PaceZones => {}
+            _ if values.len() != 1 => bail!("{} takes a single value", key.name()),
+            _ => {}
+        }
+
+        self.set_values(key, values);
+        Ok(())
+    }
+}
+
+// seconds per km as M:SS
+pub fn format_pace(seconds: f64) -> String {
+    let seconds = seconds.round() as i64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+// M:SS or plain seconds per km
+fn parse_pace(value: &str) -> Option<f64> {
+    match value.split_once(':') {
+        Some((minutes, seconds)) => {
+            let minutes: u32 = minutes.parse().ok()?;
+            let seconds: u32 = seconds.parse().ok().filter(|s| *s < 60)?;
+            Some((minutes * 60 + seconds) as f64)
+        }
+        None => value.parse().ok(),
+    }
+}
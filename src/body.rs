@@ -0,0 +1,210 @@
+use crate::athlete::Athlete;
+use crate::config::Config;
+use crate::timestamps;
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone};
+use rusqlite::types::Value;
+use rusqlite::{Connection, OptionalExtension, params, params_from_iter};
+use serde::Serialize;
+
+// only the first user of a multi-user scale is taken as the athlete
+const ATHLETE_CONDITION: &str = "weight IS NOT NULL AND (user_index IS NULL OR user_index = 0)";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BodyEntry {
+    pub timestamp: DateTime<Local>,
+    pub user_index: Option<i64>,
+    // in kg
+    pub weight: Option<f64>,
+    // in %
+    pub body_fat: Option<f64>,
+    // in kg
+    pub muscle_mass: Option<f64>,
+    pub bone_mass: Option<f64>,
+    // in %
+    pub hydration: Option<f64>,
+    pub bmi: Option<f64>,
+}
+
+// weight change over a list of measurements
+#[derive(Debug, Clone, Serialize)]
+pub struct BodyTrend {
+    // in kg
+    pub first: f64,
+    pub last: f64,
+    pub change: f64,
+    // least squares slope in kg per week, needs measurements on two days
+    pub per_week: Option<f64>,
+    // change of body fat in % points
+    pub body_fat_change: Option<f64>,
+}
+
+impl BodyEntry {
+    // measurements of one scale user between two epoch timestamps, oldest first;
+    // single-user scales record no user, which counts as the first one
+    pub fn load(
+        conn: &Connection,
+        start: i64,
+        end: i64,
+        user_index: i64,
+    ) -> anyhow::Result<Vec<BodyEntry>> {
+        let user_condition = if user_index == 0 {
+            "(user_index IS NULL OR user_index = 0)"
+        } else {
+            "user_index = ?3"
+        };
+        let query = format!(
+            "SELECT timestamp, user_index, weight, body_fat, muscle_mass, bone_mass, hydration, bmi FROM body_metrics WHERE timestamp >= ?1 AND timestamp < ?2 AND {} ORDER BY timestamp",
+            user_condition
+        );
+        let mut values = vec![Value::Integer(start), Value::Integer(end)];
+        if user_index != 0 {
+            values.push(Value::Integer(user_index));
+        }
+
+        let mut stmt = conn.prepare(&query)?;
+        let entries = stmt
+            .query_map(params_from_iter(&values), |row| {
+                Ok(BodyEntry {
+                    timestamp: timestamps::local_time(row, 0)?,
+                    user_index: row.get(1)?,
+                    weight: row.get(2)?,
+                    body_fat: row.get(3)?,
+                    muscle_mass: row.get(4)?,
+                    bone_mass: row.get(5)?,
+                    hydration: row.get(6)?,
+                    bmi: row.get(7)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(entries)
+    }
+}
+
+impl BodyTrend {
+    pub fn compute(entries: &[BodyEntry]) -> Option<Self> {
+        let weights: Vec<(f64, f64)> = entries
+            .iter()
+            .filter_map(|entry| {
+                let weeks = entry.timestamp.timestamp() as f64 / (7.0 * 86400.0);
+                entry.weight.map(|weight| (weeks, weight))
+            })
+            .collect();
+        let (first, last) = (weights.first()?.1, weights.last()?.1);
+
+        let n = weights.len() as f64;
+        let mean_x = weights.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = weights.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let sxx: f64 = weights.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+        let sxy: f64 = weights
+            .iter()
+            .map(|(x, y)| (x - mean_x) * (y - mean_y))
+            .sum();
+        // less than a day between measurements says nothing about a weekly trend
+        let per_week =
+            (sxx > 0.0 && weights.last()?.0 - weights.first()?.0 >= 1.0 / 7.0).then(|| sxy / sxx);
+
+        let body_fat: Vec<f64> = entries.iter().filter_map(|entry| entry.body_fat).collect();
+        let body_fat_change = match (body_fat.first(), body_fat.last()) {
+            (Some(first), Some(last)) if body_fat.len() > 1 => Some(last - first),
+            _ => None,
+        };
+
+        Some(BodyTrend {
+            first,
+            last,
+            change: last - first,
+            per_week,
+            body_fat_change,
+        })
+    }
+}
+
+// latest measured weight of the athlete on or before `date`, with the local date it was measured
+pub fn weight_on(conn: &Connection, date: NaiveDate) -> anyhow::Result<Option<(NaiveDate, f64)>> {
+    let end = date + Duration::days(1);
+    let end = Local
+        .from_local_datetime(&end.and_hms_opt(0, 0, 0).expect("midnight is always valid"))
+        .earliest()
+        .map_or(i64::MAX, |end| end.timestamp());
+
+    let weight = conn
+        .query_row(
+            &format!(
+                "SELECT timestamp, weight FROM body_metrics WHERE {} AND timestamp < ?1 ORDER BY timestamp DESC LIMIT 1",
+                ATHLETE_CONDITION
+            ),
+            params![end],
+            |row| Ok((timestamps::local_time(row, 0)?.date_naive(), row.get(1)?)),
+        )
+        .optional()?;
+
+    Ok(weight)
+}
+
+// athlete profile in effect on `date`, with the weight measured by a scale over the configured
+// one, unless the configured weight was entered with a later `since` than the measurement
+pub fn athlete_on(config: &Config, conn: &Connection, date: NaiveDate) -> anyhow::Result<Athlete> {
+    let mut athlete = config.get_athlete(date);
+    let configured_since = config
+        .get_athlete_entries()
+        .iter()
+        .filter(|entry| entry.weight.is_some())
+        .filter_map(|entry| entry.since)
+        .filter(|since| *since <= date)
+        .max();
+    if let Some((measured, weight)) = weight_on(conn, date)?
+        && configured_since.is_none_or(|since| measured > since)
+    {
+        athlete.weight = Some(weight);
+    }
+    Ok(athlete)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database(measurements: &[(&str, f64)]) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE body_metrics (id INTEGER PRIMARY KEY, timestamp INTEGER NOT NULL, user_index INTEGER, weight REAL);",
+        )
+        .unwrap();
+        for (date, weight) in measurements {
+            let time = date
+                .parse::<NaiveDate>()
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap();
+            let timestamp = Local.from_local_datetime(&time).unwrap().timestamp();
+            conn.execute(
+                "INSERT INTO body_metrics (timestamp, user_index, weight) VALUES (?1, 0, ?2)",
+                params![timestamp, weight],
+            )
+            .unwrap();
+        }
+        conn
+    }
+
+    fn weight(config: &str, conn: &Connection, date: &str) -> Option<f64> {
+        let config: Config = toml::from_str(config).unwrap();
+        athlete_on(&config, conn, date.parse().unwrap())
+            .unwrap()
+            .weight
+    }
+
+    #[test]
+    fn measured_weight_over_older_configured_weight() {
+        let conn = database(&[("2026-03-10", 74.5), ("2026-05-01", 73.0)]);
+        let config = "data = '/data'\n[[athlete]]\nweight = 76.0\n[[athlete]]\nsince = '2026-04-01'\nweight = 75.0";
+
+        // the undated entry is older than any measurement
+        assert_eq!(weight(config, &conn, "2026-03-20"), Some(74.5));
+        // an entry since 2026-04-01 is newer than the measurement of 2026-03-10
+        assert_eq!(weight(config, &conn, "2026-04-15"), Some(75.0));
+        assert_eq!(weight(config, &conn, "2026-05-02"), Some(73.0));
+        // nothing measured yet
+        assert_eq!(weight(config, &conn, "2026-03-01"), Some(76.0));
+    }
+}
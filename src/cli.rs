@@ -1,6 +1,7 @@
 use crate::commands;
 use crate::config::Config;
-use crate::db::Database;
+use crate::db::{Database, REIMPORT_MESSAGE};
+use crate::output::OutputFormat;
 use clap::{Parser, Subcommand};
 use std::process;
 
@@ -10,6 +11,10 @@ use std::process;
 pub struct Cli {
     #[command(subcommand)]
     pub commands: Commands,
+
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    #[arg(help = "output format")]
+    pub format: OutputFormat,
 }
 
 #[derive(Debug, Subcommand)]
@@ -30,6 +35,14 @@ pub enum Commands {
     #[command(about = "display summary over specified time period")]
     Summary(commands::SummaryArgs),
 
+    #[command(name = "activity")]
+    #[command(about = "inspect a single activity")]
+    Activity(commands::ActivityArgs),
+
+    #[command(name = "query")]
+    #[command(about = "list activities matching a filter expression")]
+    Query(commands::QueryArgs),
+
     #[command(name = "devices")]
     #[command(about = "get information on devices")]
     Devices(commands::DevicesArgs),
@@ -37,12 +50,24 @@ pub enum Commands {
     #[command(name = "calculate")]
     #[command(about = "calculate something from workout data")]
     Calculate(commands::CalculateArgs),
+
+    #[command(name = "strength")]
+    #[command(about = "strength training volume and estimated 1RM progression")]
+    Strength(commands::StrengthArgs),
+
+    #[command(name = "wellness")]
+    #[command(about = "daily steps, resting heart rate, sleep, stress and HRV with trends")]
+    Wellness(commands::WellnessArgs),
+
+    #[command(name = "body")]
+    #[command(about = "weight and body composition from weight scales")]
+    Body(commands::BodyArgs),
 }
 
 impl Cli {
     pub fn run(self) -> anyhow::Result<()> {
         if let Commands::Config(cmd) = &self.commands {
-            return cmd.run();
+            return cmd.run(self.format);
         }
 
         let config = Config::load()?;
@@ -50,16 +75,15 @@ impl Cli {
         let db = Database::new(&config)?;
 
         if db.initialized()? {
-            if !db.correct_version()? {
+            db.migrate()?;
+            if db.reimport_required()? {
                 db.set_db_invalid();
-                // if database version is not correct, only allow database commands
+                // if a migration needs data from the .fit files, only allow database commands
                 match &self.commands {
-                    Commands::Config(cmd) => cmd.run()?,
-                    Commands::Info(cmd) => cmd.run(&config, &db)?,
-                    Commands::Database(cmd) => cmd.run(&config, &db)?,
-                    _ => println!(
-                        "The database version does not match the app version.\n Please run'queryfit database recreate'.\nNo data will be lost."
-                    ),
+                    Commands::Config(cmd) => cmd.run(self.format)?,
+                    Commands::Info(cmd) => cmd.run(&config, &db, self.format)?,
+                    Commands::Database(cmd) => cmd.run(&config, &db, self.format)?,
+                    _ => println!("{}", REIMPORT_MESSAGE),
                 }
                 process::exit(0);
             }
@@ -68,12 +92,17 @@ impl Cli {
         }
 
         match self.commands {
-            Commands::Config(cmd) => cmd.run(),
-            Commands::Info(cmd) => cmd.run(&config, &db),
-            Commands::Database(cmd) => cmd.run(&config, &db),
-            Commands::Summary(cmd) => cmd.run(&config, &db),
-            Commands::Devices(cmd) => cmd.run(&config, &db),
-            Commands::Calculate(cmd) => cmd.run(&config, &db),
+            Commands::Config(cmd) => cmd.run(self.format),
+            Commands::Info(cmd) => cmd.run(&config, &db, self.format),
+            Commands::Database(cmd) => cmd.run(&config, &db, self.format),
+            Commands::Summary(cmd) => cmd.run(&config, &db, self.format),
+            Commands::Activity(cmd) => cmd.run(&config, &db, self.format),
+            Commands::Query(cmd) => cmd.run(&config, &db, self.format),
+            Commands::Devices(cmd) => cmd.run(&config, &db, self.format),
+            Commands::Calculate(cmd) => cmd.run(&config, &db, self.format),
+            Commands::Strength(cmd) => cmd.run(&config, &db, self.format),
+            Commands::Wellness(cmd) => cmd.run(&config, &db, self.format),
+            Commands::Body(cmd) => cmd.run(&config, &db, self.format),
         }
     }
 }
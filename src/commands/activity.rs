@@ -0,0 +1,482 @@
+use crate::commands::SummaryArgs;
+use crate::config::Config;
+use crate::db::Database;
+use crate::output::OutputFormat;
+use crate::swim::{Swim, SwimSummary};
+use crate::timestamps;
+use anyhow::anyhow;
+use chrono::{DateTime, FixedOffset};
+use clap::{Args, Subcommand};
+use rusqlite::{OptionalExtension, params};
+use serde::Serialize;
+
+#[derive(Debug, Args)]
+pub struct ActivityArgs {
+    #[command(subcommand)]
+    pub actions: Actions,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Actions {
+    #[command(name = "show")]
+    #[command(about = "show an activity with its source file and devices")]
+    Show(ShowArgs),
+
+    #[command(name = "laps")]
+    #[command(about = "list the laps of an activity")]
+    Laps(LapsArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ShowArgs {
+    #[arg(help = "activity id, defaults to the latest activity")]
+    pub id: Option<i64>,
+}
+
+#[derive(Debug, Args)]
+pub struct LapsArgs {
+    #[arg(help = "activity id, defaults to the latest activity")]
+    pub id: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActivityHeader {
+    pub id: i64,
+    pub sport: String,
+    // in the timezone the activity was recorded in
+    pub timestamp: DateTime<FixedOffset>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActivityDetail {
+    pub sub_sport: Option<String>,
+    // sport profile of the device
+    pub profile: Option<String>,
+    // planned workout that was followed
+    pub workout: Option<String>,
+    // in seconds
+    pub duration: Option<f64>,
+    // in meters
+    pub distance: Option<f64>,
+    pub avg_hr: Option<f64>,
+    pub avg_power: Option<f64>,
+    pub calories: Option<f64>,
+    // in meters
+    pub elevation: Option<f64>,
+    pub rpe: Option<f64>,
+    pub rpe_est: Option<bool>,
+    // in m, only for pool swims
+    pub pool_length: Option<f64>,
+    // path of the .fit file the activity was imported from
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActivityDevice {
+    pub activity_id: i64,
+    pub product: String,
+    // in V
+    pub battery: Option<f64>,
+    pub battery_status: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ShowReport<'a> {
+    activity: &'a ActivityHeader,
+    detail: &'a ActivityDetail,
+    devices: &'a [ActivityDevice],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    swim: Option<&'a SwimSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LapEntry {
+    pub activity_id: i64,
+    pub lap: i64,
+    pub start_time: DateTime<FixedOffset>,
+    // in seconds
+    pub duration: f64,
+    // in meters
+    pub distance: Option<f64>,
+    pub avg_hr: Option<f64>,
+    pub max_hr: Option<f64>,
+    pub avg_power: Option<f64>,
+    pub avg_cadence: Option<f64>,
+    pub trigger: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct LapsReport<'a> {
+    activity: &'a ActivityHeader,
+    laps: &'a [LapEntry],
+}
+
+impl ActivityArgs {
+    pub fn run(&self, _config: &Config, db: &Database, format: OutputFormat) -> anyhow::Result<()> {
+        match &self.actions {
+            Actions::Show(args) => {
+                Self::run_show(args, db, format)?;
+            }
+            Actions::Laps(args) => {
+                Self::run_laps(args, db, format)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn run_show(args: &ShowArgs, db: &Database, format: OutputFormat) -> anyhow::Result<()> {
+        let Some(activity) = Self::get_activity(db, args.id)? else {
+            println!("No activities in the database.");
+            return Ok(());
+        };
+        let detail = Self::get_detail(db, activity.id)?;
+        let devices = Self::get_devices(db, activity.id)?;
+        let swims = Swim::load(db.connection(), "id = ?1", &[activity.id.into()])?;
+        let swim = SwimSummary::compute(&swims);
+
+        let report = ShowReport {
+            activity: &activity,
+            detail: &detail,
+            devices: &devices,
+            swim: swim.as_ref(),
+        };
+        format.print_document(&report, &devices, || {
+            Self::print_show(&activity, &detail, &devices, swim.as_ref());
+            Ok(())
+        })
+    }
+
+    fn print_show(
+        activity: &ActivityHeader,
+        detail: &ActivityDetail,
+        devices: &[ActivityDevice],
+        swim: Option<&SwimSummary>,
+    ) {
+        println!(
+            "Activity {}: {}, {}\n",
+            activity.id,
+            activity.sport,
+            activity.timestamp.format("%Y-%m-%d %H:%M")
+        );
+
+        println!(
+            "{:<12}: {}",
+            "Sub-sport",
+            detail.sub_sport.as_deref().unwrap_or("N/A")
+        );
+        if let Some(profile) = &detail.profile {
+            println!("{:<12}: {}", "Profile", profile);
+        }
+        if let Some(workout) = &detail.workout {
+            println!("{:<12}: {}", "Workout", workout);
+        }
+
+        let duration = detail.duration.unwrap_or(0.0);
+        println!("{:<12}: {}", "Duration", Self::format_time(duration));
+        println!(
+            "{:<12}: {}",
+            "Distance",
+            detail
+                .distance
+                .map(|d| format!("{:.2} km", d / 1000.0))
+                .unwrap_or_else(|| "N/A".to_string())
+        );
+        if detail.distance.is_some() {
+            let speed_label = if Self::uses_pace(&activity.sport) {
+                "Pace"
+            } else {
+                "Speed"
+            };
+            println!(
+                "{:<12}: {}",
+                speed_label,
+                Self::format_speed(&activity.sport, duration, detail.distance)
+            );
+        }
+        println!("{:<12}: {}", "Avg HR", Self::format_optional(detail.avg_hr));
+        println!(
+            "{:<12}: {}",
+            "Avg power",
+            Self::format_optional(detail.avg_power)
+        );
+        println!(
+            "{:<12}: {}",
+            "Calories",
+            Self::format_optional(detail.calories)
+        );
+        println!(
+            "{:<12}: {}",
+            "Elevation",
+            Self::format_optional(detail.elevation)
+        );
+        println!(
+            "{:<12}: {}{}",
+            "RPE",
+            detail
+                .rpe
+                .map(|rpe| format!("{:.1}", rpe))
+                .unwrap_or_else(|| "N/A".to_string()),
+            if detail.rpe_est == Some(true) {
+                " (estimated)"
+            } else {
+                ""
+            }
+        );
+        println!(
+            "{:<12}: {}",
+            "File",
+            detail.file.as_deref().unwrap_or("N/A")
+        );
+
+        if let Some(swim) = swim {
+            match detail.pool_length {
+                Some(pool_length) => println!("\nSwimming, {:.0} m pool:", pool_length),
+                None => println!("\nSwimming:"),
+            }
+            SummaryArgs::print_swim(swim);
+        }
+
+        println!("\nDevices:");
+        if devices.is_empty() {
+            println!("  No devices recorded.");
+        }
+        for device in devices {
+            println!(
+                "  {:<20} {:<10} {}",
+                device.product,
+                device
+                    .battery
+                    .map(|voltage| format!("{voltage:.2} V"))
+                    .unwrap_or_else(|| "N/A".to_string()),
+                device.battery_status.as_deref().unwrap_or("")
+            );
+        }
+    }
+
+    fn run_laps(args: &LapsArgs, db: &Database, format: OutputFormat) -> anyhow::Result<()> {
+        let Some(activity) = Self::get_activity(db, args.id)? else {
+            println!("No activities in the database.");
+            return Ok(());
+        };
+        let laps = Self::get_laps(db, &activity)?;
+
+        let report = LapsReport {
+            activity: &activity,
+            laps: &laps,
+        };
+        format.print_document(&report, &laps, || {
+            Self::print_laps(&activity, &laps);
+            Ok(())
+        })
+    }
+
+    fn print_laps(activity: &ActivityHeader, laps: &[LapEntry]) {
+        println!(
+            "Activity {}: {}, {}\n",
+            activity.id,
+            activity.sport,
+            activity.timestamp.format("%Y-%m-%d %H:%M")
+        );
+
+        if laps.is_empty() {
+            println!("No laps recorded.");
+            return;
+        }
+
+        let speed_label = if Self::uses_pace(&activity.sport) {
+            "Pace"
+        } else {
+            "Speed"
+        };
+        println!(
+            "{:<4} {:>9} {:>9} {:>10} {:>6} {:>6} {:>6} {:>7}  Trigger",
+            "Lap", "Time", "Distance", speed_label, "Avg HR", "Max HR", "Power", "Cadence"
+        );
+        for lap in laps {
+            println!(
+                "{:<4} {:>9} {:>9} {:>10} {:>6} {:>6} {:>6} {:>7}  {}",
+                lap.lap,
+                Self::format_time(lap.duration),
+                lap.distance
+                    .map(|d| format!("{:.2} km", d / 1000.0))
+                    .unwrap_or_else(|| "N/A".to_string()),
+                Self::format_speed(&activity.sport, lap.duration, lap.distance),
+                Self::format_optional(lap.avg_hr),
+                Self::format_optional(lap.max_hr),
+                Self::format_optional(lap.avg_power),
+                Self::format_optional(lap.avg_cadence),
+                lap.trigger.as_deref().unwrap_or("")
+            );
+        }
+    }
+
+    // the given activity, or the latest one; None if the database has no activities
+    fn get_activity(db: &Database, id: Option<i64>) -> anyhow::Result<Option<ActivityHeader>> {
+        let query = match id {
+            Some(_) => "SELECT id, sport, timestamp, utc_offset FROM activities WHERE id = ?1",
+            None => {
+                "SELECT id, sport, timestamp, utc_offset FROM activities ORDER BY timestamp DESC LIMIT 1"
+            }
+        };
+
+        let mut stmt = db.connection().prepare(query)?;
+        let map_row = |row: &rusqlite::Row| {
+            Ok(ActivityHeader {
+                id: row.get(0)?,
+                sport: row.get(1)?,
+                timestamp: timestamps::activity_time(row, 2, 3)?,
+            })
+        };
+        let activity = match id {
+            Some(id) => stmt.query_row(params![id], map_row).optional()?,
+            None => stmt.query_row(params![], map_row).optional()?,
+        };
+
+        match (id, activity) {
+            (Some(id), None) => Err(anyhow!("No activity with id {}", id)),
+            (_, activity) => Ok(activity),
+        }
+    }
+
+    fn get_detail(db: &Database, activity_id: i64) -> anyhow::Result<ActivityDetail> {
+        let detail = db.connection().query_row(
+            "SELECT sub_sport, profile, workout, duration, distance, avg_hr, avg_power, calories, elevation, rpe, rpe_est, pool_length, files.path FROM activities LEFT JOIN files ON files.id = activities.file_id WHERE activities.id = ?1",
+            params![activity_id],
+            |row| {
+                Ok(ActivityDetail {
+                    sub_sport: row.get(0)?,
+                    profile: row.get(1)?,
+                    workout: row.get(2)?,
+                    duration: row.get(3)?,
+                    distance: row.get(4)?,
+                    avg_hr: row.get(5)?,
+                    avg_power: row.get(6)?,
+                    calories: row.get(7)?,
+                    elevation: row.get(8)?,
+                    rpe: row.get(9)?,
+                    rpe_est: row.get(10)?,
+                    pool_length: row.get(11)?,
+                    file: row.get(12)?,
+                })
+            },
+        )?;
+
+        Ok(detail)
+    }
+
+    // one row per product, with the battery of its last reading during the activity
+    fn get_devices(db: &Database, activity_id: i64) -> anyhow::Result<Vec<ActivityDevice>> {
+        let mut stmt = db.connection().prepare(
+            "
+            WITH used AS (
+                SELECT devices.*
+                FROM activity_devices
+                JOIN devices ON devices.id = activity_devices.device_id
+                WHERE activity_devices.activity_id = ?1
+            )
+            SELECT
+                product,
+                (
+                    SELECT latest.battery
+                    FROM used AS latest
+                    WHERE latest.product = used.product
+                      AND latest.battery IS NOT NULL
+                    ORDER BY latest.timestamp DESC, latest.id DESC
+                    LIMIT 1
+                ) AS battery,
+                (
+                    SELECT latest.battery_status
+                    FROM used AS latest
+                    WHERE latest.product = used.product
+                      AND latest.battery_status IS NOT NULL
+                      AND latest.battery_status != ''
+                    ORDER BY latest.timestamp DESC, latest.id DESC
+                    LIMIT 1
+                ) AS battery_status
+            FROM used
+            GROUP BY product
+            ORDER BY product
+            ",
+        )?;
+
+        let devices = stmt
+            .query_map(params![activity_id], |row| {
+                Ok(ActivityDevice {
+                    activity_id,
+                    product: row.get(0)?,
+                    battery: row.get(1)?,
+                    battery_status: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<ActivityDevice>, _>>()?;
+
+        Ok(devices)
+    }
+
+    // lap times are shown in the activity's timezone
+    fn get_laps(db: &Database, activity: &ActivityHeader) -> anyhow::Result<Vec<LapEntry>> {
+        let mut stmt = db.connection().prepare(
+            "SELECT lap, start_time, duration, distance, avg_hr, max_hr, avg_power, avg_cadence, lap_trigger FROM laps WHERE activity_id = ?1 ORDER BY lap",
+        )?;
+
+        let laps = stmt
+            .query_map(params![activity.id], |row| {
+                let start_time = timestamps::local_time(row, 1)?;
+
+                Ok(LapEntry {
+                    activity_id: activity.id,
+                    lap: row.get(0)?,
+                    start_time: start_time.with_timezone(activity.timestamp.offset()),
+                    duration: row.get::<_, Option<f64>>(2)?.unwrap_or(0.0),
+                    distance: row.get(3)?,
+                    avg_hr: row.get(4)?,
+                    max_hr: row.get(5)?,
+                    avg_power: row.get(6)?,
+                    avg_cadence: row.get(7)?,
+                    trigger: row.get(8)?,
+                })
+            })?
+            .collect::<Result<Vec<LapEntry>, _>>()?;
+
+        Ok(laps)
+    }
+
+    // foot and swim sports are read as pace, everything else as speed
+    fn uses_pace(sport: &str) -> bool {
+        matches!(sport, "running" | "walking" | "hiking" | "swimming")
+    }
+
+    fn format_speed(sport: &str, duration: f64, distance: Option<f64>) -> String {
+        let Some(distance) = distance.filter(|d| *d > 0.0 && duration > 0.0) else {
+            return "N/A".to_string();
+        };
+
+        if sport == "swimming" {
+            let pace = (duration / (distance / 100.0)).round() as u64;
+            format!("{}:{:02}/100m", pace / 60, pace % 60)
+        } else if Self::uses_pace(sport) {
+            let pace = (duration / (distance / 1000.0)).round() as u64;
+            format!("{}:{:02}/km", pace / 60, pace % 60)
+        } else {
+            format!("{:.1} km/h", distance / duration * 3.6)
+        }
+    }
+
+    // m:ss, or h:mm:ss for an hour or more
+    fn format_time(seconds: f64) -> String {
+        let seconds = seconds.round() as u64;
+        let (hours, mins, secs) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+        if hours > 0 {
+            format!("{}:{:02}:{:02}", hours, mins, secs)
+        } else {
+            format!("{}:{:02}", mins, secs)
+        }
+    }
+
+    fn format_optional(value: Option<f64>) -> String {
+        match value {
+            Some(value) => format!("{:.0}", value),
+            None => "N/A".to_string(),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::body::{self, BodyEntry, BodyTrend};
+use crate::config::Config;
+use crate::db::Database;
+use crate::output::OutputFormat;
+use crate::period::{PERIOD_HELP, Period};
+use chrono::Local;
+use clap::Args;
+use serde::Serialize;
+
+#[derive(Debug, Args)]
+pub struct BodyArgs {
+    #[arg(long, value_parser = Period::parse, default_value = "90d")]
+    #[arg(help = PERIOD_HELP)]
+    pub period: Period,
+
+    #[arg(long, default_value_t = 0)]
+    #[arg(help = "user of a multi-user scale, 0 is the first user and the athlete")]
+    pub user: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct BodyReport<'a> {
+    period: String,
+    measurements: &'a [BodyEntry],
+    trend: Option<BodyTrend>,
+    // current weight and FTP of the athlete profile
+    weight: Option<f64>,
+    ftp: Option<f64>,
+    watts_per_kg: Option<f64>,
+}
+
+impl BodyArgs {
+    pub fn run(&self, config: &Config, db: &Database, format: OutputFormat) -> anyhow::Result<()> {
+        let (start, end) = self.period.local_bounds();
+        let measurements = BodyEntry::load(
+            db.connection(),
+            start.timestamp(),
+            end.timestamp(),
+            self.user,
+        )?;
+        let trend = BodyTrend::compute(&measurements);
+
+        let athlete = body::athlete_on(config, db.connection(), Local::now().date_naive())?;
+        let watts_per_kg = match (athlete.ftp, athlete.weight) {
+            (Some(ftp), Some(weight)) => Some(ftp / weight),
+            _ => None,
+        };
+
+        let report = BodyReport {
+            period: self.period.label(),
+            measurements: &measurements,
+            trend,
+            weight: athlete.weight,
+            ftp: athlete.ftp,
+            watts_per_kg,
+        };
+        format.print_document(&report, &measurements, || {
+            Self::print_body(&report);
+            Ok(())
+        })
+    }
+
+    fn print_body(report: &BodyReport) {
+        println!("Body metrics, {}", report.period);
+        if report.measurements.is_empty() {
+            println!("\nNo measurements, import .fit files from a weight scale.");
+        } else {
+            println!(
+                "\n{:<16} {:>8} {:>7} {:>8} {:>7} {:>7} {:>6}",
+                "Date", "Weight", "Fat", "Muscle", "Bone", "Water", "BMI"
+            );
+            for entry in report.measurements {
+                println!(
+                    "{:<16} {:>8} {:>7} {:>8} {:>7} {:>7} {:>6}",
+                    entry.timestamp.format("%Y-%m-%d %H:%M"),
+                    Self::format_value(entry.weight, 1, " kg"),
+                    Self::format_value(entry.body_fat, 1, "%"),
+                    Self::format_value(entry.muscle_mass, 1, " kg"),
+                    Self::format_value(entry.bone_mass, 1, " kg"),
+                    Self::format_value(entry.hydration, 1, "%"),
+                    Self::format_value(entry.bmi, 1, "")
+                );
+            }
+        }
+
+        if let Some(trend) = &report.trend {
+            println!(
+                "\nWeight: {:.1} kg -> {:.1} kg ({:+.1} kg)",
+                trend.first, trend.last, trend.change
+            );
+            if let Some(per_week) = trend.per_week {
+                println!("Trend: {:+.2} kg per week", per_week);
+            }
+            if let Some(change) = trend.body_fat_change {
+                println!("Body fat: {:+.1}% points", change);
+            }
+        }
+
+        match (report.weight, report.watts_per_kg) {
+            (Some(weight), Some(watts_per_kg)) => println!(
+                "\nAthlete weight: {:.1} kg, FTP {:.0} W = {:.2} W/kg",
+                weight,
+                report.ftp.unwrap_or(0.0),
+                watts_per_kg
+            ),
+            (Some(weight), None) => println!("\nAthlete weight: {:.1} kg", weight),
+            _ => {}
+        }
+    }
+
+    fn format_value(value: Option<f64>, precision: usize, unit: &str) -> String {
+        value
+            .map(|value| format!("{:.*}{}", precision, value, unit))
+            .unwrap_or_else(|| "-".to_string())
+    }
+}
@@ -1,11 +1,22 @@
+use crate::athlete;
+use crate::body;
 use crate::config::Config;
 use crate::db::Database;
+use crate::efforts::{BEST_DISTANCES, CriticalFit, EffortKind};
+use crate::filter::{Condition, Field};
+use crate::load::{self, ActivityLoad, DailyLoad, LoadInput, LoadMethod, Thresholds};
+use crate::output::OutputFormat;
+use crate::period::{PERIOD_HELP, Period};
+use crate::rpe::{MIN_SAMPLES, RpeModel, RpeModels, Sample};
+use crate::streams::Stream;
+use crate::timestamps;
+use crate::zones::TimeInZones;
+use chrono::{DateTime, FixedOffset, Local};
 use clap::{Args, Subcommand};
-use linfa::dataset::DatasetBase;
-use linfa::prelude::{Fit, Predict};
-use linfa_linear::{FittedLinearRegression, LinearRegression};
-use ndarray::{Array1, Array2, ArrayBase, Dim, OwnedRepr};
-use std::f64;
+use rusqlite::params;
+use rusqlite::types::Value;
+use serde::Serialize;
+use std::collections::BTreeSet;
 
 #[derive(Debug, Args)]
 pub struct CalculateArgs {
@@ -17,192 +28,701 @@ pub struct CalculateArgs {
 pub enum Actions {
     #[command(name = "rpe")]
     #[command(about = "calculate RPE for workouts without RPE")]
-    RPE,
+    Rpe(RpeArgs),
+
+    #[command(name = "load")]
+    #[command(about = "calculate training load, fitness (CTL), fatigue (ATL) and form (TSB)")]
+    Load(LoadArgs),
+
+    #[command(name = "power-curve")]
+    #[command(
+        about = "best power efforts, and pace and distance efforts for running only, with critical power, W' and critical speed estimates"
+    )]
+    PowerCurve(PowerCurveArgs),
+
+    #[command(name = "zones")]
+    #[command(
+        about = "recalculate time in zones from imported records, e.g. after changing zones (pace zones for running only)"
+    )]
+    Zones,
 }
 
-pub struct TrainingWorkout {
-    avg_hr: f64,
-    avg_power: f64,
-    elevation: f64,
-    duration: f64,
-    distance: f64,
-    rpe: f64,
+#[derive(Debug, Args)]
+pub struct RpeArgs {
+    #[arg(long, help = "print estimates without saving them or the models")]
+    pub dry_run: bool,
+
+    #[arg(
+        long,
+        help = "retrain the models and re-estimate previously estimated workouts"
+    )]
+    pub retrain: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct LoadArgs {
+    #[arg(long, value_enum, default_value_t = LoadMethod::Auto)]
+    #[arg(help = "per-activity load method (auto prefers TSS, then TRIMP, then session-RPE)")]
+    pub method: LoadMethod,
+
+    #[arg(
+        long,
+        help = "maximum heart rate for TRIMP, overrides the athlete profile"
+    )]
+    pub max_hr: Option<f64>,
+
+    #[arg(
+        long,
+        help = "resting heart rate for TRIMP, overrides the athlete profile"
+    )]
+    pub resting_hr: Option<f64>,
+
+    #[arg(
+        long,
+        help = "functional threshold power in W for TSS, overrides the athlete profile"
+    )]
+    pub ftp: Option<f64>,
+
+    #[arg(
+        long,
+        default_value_t = 42,
+        help = "number of most recent days to show"
+    )]
+    pub days: usize,
+
+    #[arg(long, default_value_t = 8.0)]
+    #[arg(help = "weekly CTL increase above which a ramp rate warning is shown")]
+    pub max_ramp: f64,
+}
+
+#[derive(Debug, Args)]
+pub struct PowerCurveArgs {
+    #[arg(long, value_parser = Period::parse, default_value = "90d")]
+    #[arg(help = PERIOD_HELP)]
+    pub period: Period,
+
+    #[arg(
+        long,
+        conflicts_with = "period",
+        help = "best efforts of a single activity"
+    )]
+    pub id: Option<i64>,
+
+    #[arg(long, help = "only include these activity types")]
+    pub activity: Option<Vec<String>>,
+}
+
+// best effort of the period and the activity it was set in
+#[derive(Debug, Serialize)]
+pub struct EffortEntry {
+    pub kind: EffortKind,
+    // seconds for power and pace, meters for distance
+    pub target: f64,
+    // W for power, m/s for pace and distance
+    pub value: f64,
+    pub activity_id: i64,
+    pub timestamp: DateTime<FixedOffset>,
+}
+
+#[derive(Debug, Serialize)]
+struct PowerCurveReport<'a> {
+    period: String,
+    efforts: &'a [EffortEntry],
+    critical_power: Option<CriticalFit>,
+    ftp_estimate: Option<f64>,
+    critical_speed: Option<CriticalFit>,
+    // in kg, from the athlete profile or scale
+    weight: Option<f64>,
 }
 
-pub struct Workout {
-    avg_hr: f64,
-    avg_power: f64,
-    elevation: f64,
-    duration: f64,
-    distance: f64,
+#[derive(Debug, Serialize)]
+struct LoadReport<'a> {
+    activities: &'a [ActivityLoad],
+    days: &'a [DailyLoad],
+    skipped: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RpeEstimate {
+    pub id: i64,
+    pub sport: String,
+    // in km
+    pub distance: Option<f64>,
+    pub avg_hr: Option<f64>,
+    pub avg_power: Option<f64>,
+    pub estimated_rpe: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct RpeReport<'a> {
+    models: &'a [RpeModel],
+    estimates: &'a [RpeEstimate],
 }
 
 impl CalculateArgs {
-    pub fn run(&self, config: &Config, db: &Database) -> anyhow::Result<()> {
+    pub fn run(&self, config: &Config, db: &Database, format: OutputFormat) -> anyhow::Result<()> {
         match &self.actions {
-            Actions::RPE => {
-                Self::run_rpe(&self, config, db)?;
+            Actions::Rpe(args) => {
+                Self::run_rpe(args, config, db, format)?;
+            }
+            Actions::Load(args) => {
+                Self::run_load(args, config, db, format)?;
+            }
+            Actions::PowerCurve(args) => {
+                Self::run_power_curve(args, config, db, format)?;
+            }
+            Actions::Zones => {
+                Self::run_zones(config, db)?;
             }
         }
         Ok(())
     }
-    fn run_rpe(&self, config: &Config, db: &Database) -> anyhow::Result<()> {
-        let training_workouts = Self::fetch_training_workouts(db)?;
-        let (features, targets) = Self::prepare_data(&training_workouts);
-        let (scaled_features, scaling_params) = Self::scale_features(&features);
-        // println!("scaled_features: {:?}, scaling_params: {:?}", scaled_features, scaling_params);
 
-        // convert to ndarray
-        let features_array = Array2::from_shape_vec(
-            (scaled_features.len(), scaled_features[0].len()),
-            scaled_features.into_iter().flatten().collect(),
-        )?;
+    fn run_rpe(
+        args: &RpeArgs,
+        config: &Config,
+        db: &Database,
+        format: OutputFormat,
+    ) -> anyhow::Result<()> {
+        let samples = Self::fetch_samples(db)?;
+
+        // reuse saved models so estimates stay stable until the models are retrained
+        let saved = if args.retrain {
+            None
+        } else {
+            RpeModels::load(config)?
+        };
+        let retrained = saved.is_none();
+        let models = match saved {
+            Some(models) => models,
+            None => {
+                let models = Self::train_models(&samples)?;
+                if !args.dry_run {
+                    models.save(config)?;
+                }
+                models
+            }
+        };
+
+        if models.models.is_empty() {
+            println!(
+                "No sport has at least {} workouts with a logged RPE to train a model on.",
+                MIN_SAMPLES
+            );
+            return Ok(());
+        }
 
-        let targets_array = Array1::from_vec(targets);
+        let estimates: Vec<RpeEstimate> = samples
+            .iter()
+            .filter(|s| s.rpe.is_none() || (retrained && s.rpe_est))
+            .filter_map(|sample| {
+                let model = models.get(&sample.sport)?;
+                Some(RpeEstimate {
+                    id: sample.id,
+                    sport: sample.sport.clone(),
+                    distance: sample.distance.map(|d| d / 1000.0),
+                    avg_hr: sample.avg_hr,
+                    avg_power: sample.avg_power,
+                    estimated_rpe: model.predict(sample)?,
+                })
+            })
+            .collect();
 
-        // Create custom dataset
-        let dataset = DatasetBase::new(features_array, targets_array).with_feature_names(vec![
-            "avg_hr".to_string(),
-            "avg_power".to_string(),
-            "elevation".to_string(),
-            "duration".to_string(),
-            "distance".to_string(),
-        ]);
+        if !args.dry_run {
+            Self::save_estimates(db, &estimates)?;
+        }
+
+        let report = RpeReport {
+            models: &models.models,
+            estimates: &estimates,
+        };
+        format.print_document(&report, &estimates, || {
+            Self::print_models(&models.models, retrained);
+
+            println!();
+            for estimate in &estimates {
+                println!(
+                    "Estimated RPE: {:.1} ({}, dist: {}, avg_hr: {}, avg_power: {})",
+                    estimate.estimated_rpe,
+                    estimate.sport,
+                    Self::format_optional(estimate.distance, 2),
+                    Self::format_optional(estimate.avg_hr, 0),
+                    Self::format_optional(estimate.avg_power, 0),
+                );
+            }
 
-        let model = LinearRegression::new()
-            .fit(&dataset)
-            .expect("Failed to train model");
+            if !args.dry_run {
+                println!("Saved {} estimated RPE values.", estimates.len());
+            }
+
+            Ok(())
+        })
+    }
+
+    fn run_load(
+        args: &LoadArgs,
+        config: &Config,
+        db: &Database,
+        format: OutputFormat,
+    ) -> anyhow::Result<()> {
+        let inputs = Self::fetch_load_inputs(db)?;
+        let mut activities = Vec::new();
+        for input in &inputs {
+            // options override the athlete profile in effect on the activity's date
+            let athlete = body::athlete_on(config, db.connection(), input.timestamp.date_naive())?;
+            let thresholds = Thresholds {
+                max_hr: args.max_hr.or(athlete.max_hr),
+                resting_hr: args.resting_hr.or(athlete.resting_hr),
+                ftp: args.ftp.or(athlete.ftp),
+            };
+            let Some((method, load)) = input.load(args.method, &thresholds) else {
+                continue;
+            };
+            activities.push(ActivityLoad {
+                id: input.id,
+                timestamp: input.timestamp,
+                sport: input.sport.clone(),
+                method,
+                load,
+            });
+        }
+        let skipped = inputs.len() - activities.len();
+
+        let all_days = load::daily_loads(&activities, Local::now().date_naive(), args.max_ramp);
+        let days = &all_days[all_days.len().saturating_sub(args.days)..];
+
+        let report = LoadReport {
+            activities: &activities,
+            days,
+            skipped,
+        };
+        format.print_document(&report, days, || {
+            Self::print_load(days, skipped, args.method);
+            Ok(())
+        })
+    }
 
-        let workouts = Self::fetch_workouts(db)?;
+    fn run_power_curve(
+        args: &PowerCurveArgs,
+        config: &Config,
+        db: &Database,
+        format: OutputFormat,
+    ) -> anyhow::Result<()> {
+        let efforts = Self::fetch_best_efforts(db, args)?;
+        let curve = |kind: EffortKind| -> Vec<(f64, f64)> {
+            efforts
+                .iter()
+                .filter(|e| e.kind == kind)
+                .map(|e| (e.target, e.value))
+                .collect()
+        };
+
+        let critical_power = CriticalFit::fit(&curve(EffortKind::Power));
+        // 95% of the best 20 minute power, or critical power without a 20 minute effort
+        let ftp_estimate = curve(EffortKind::Power)
+            .iter()
+            .find(|(duration, _)| *duration == 1200.0)
+            .map(|(_, power)| power * 0.95)
+            .or(critical_power.as_ref().map(|fit| fit.critical));
+        let critical_speed = CriticalFit::fit(&curve(EffortKind::Pace));
+
+        // weight on the activity's date, or at the end of the period
+        let (period, date) = match args.id {
+            Some(id) => (
+                format!("Activity {}", id),
+                efforts.first().map(|effort| effort.timestamp.date_naive()),
+            ),
+            None => {
+                let (_, end) = args.period.date_bounds();
+                let today = Local::now().date_naive();
+                (
+                    args.period.label(),
+                    end.pred_opt().map(|last| last.min(today)),
+                )
+            }
+        };
+        let weight = match date {
+            Some(date) => body::athlete_on(config, db.connection(), date)?.weight,
+            None => None,
+        };
+        let report = PowerCurveReport {
+            period,
+            efforts: &efforts,
+            critical_power,
+            ftp_estimate,
+            critical_speed,
+            weight,
+        };
+        format.print_document(&report, &efforts, || {
+            Self::print_power_curve(&report);
+            Ok(())
+        })
+    }
 
-        for workout in workouts {
-            let estimated_rpe = Self::estimate_rpe(&workout, &scaling_params, &model)?;
-            println!("Estimated RPE: {} (dist: {}, avg_hr: {}, avg_power: {})", estimated_rpe, &workout.distance/1000.0, &workout.avg_hr, &workout.avg_power);
+    fn print_power_curve(report: &PowerCurveReport) {
+        println!("Best efforts, {}", report.period);
+        if report.efforts.is_empty() {
+            println!("\nNo best efforts, import records to calculate them.");
+            return;
         }
 
-        let coefficients = model.params();
-        println!("Coeffs: {}", coefficients);
+        for kind in EffortKind::ALL {
+            let efforts: Vec<&EffortEntry> =
+                report.efforts.iter().filter(|e| e.kind == kind).collect();
+            if efforts.is_empty() {
+                continue;
+            }
 
-        // let intercept = model.intercept();
-        // println!("Intercept: {}", intercept);
+            let (title, target_label, value_label) = match kind {
+                EffortKind::Power => ("Power curve", "Duration", "Power"),
+                EffortKind::Pace => ("Pace curve", "Duration", "Pace"),
+                EffortKind::Distance => ("Best distances", "Distance", "Time"),
+            };
+            println!("\n{}:\n", title);
+            println!(
+                "{:<10} {:>10} {:>10}  Date",
+                target_label, value_label, "Activity"
+            );
+            for effort in efforts {
+                let (target, value) = match kind {
+                    EffortKind::Power => (
+                        Self::format_effort_duration(effort.target),
+                        format!(
+                            "{:.0} W{}",
+                            effort.value,
+                            Self::format_watts_per_kg(effort.value, report.weight)
+                        ),
+                    ),
+                    EffortKind::Pace => (
+                        Self::format_effort_duration(effort.target),
+                        Self::format_pace(effort.value),
+                    ),
+                    EffortKind::Distance => (
+                        BEST_DISTANCES
+                            .iter()
+                            .find(|(distance, _)| *distance == effort.target)
+                            .map(|(_, name)| name.to_string())
+                            .unwrap_or_else(|| format!("{:.0} m", effort.target)),
+                        Self::format_clock(effort.target / effort.value),
+                    ),
+                };
+                println!(
+                    "{:<10} {:>10} {:>10}  {}",
+                    target,
+                    value,
+                    effort.activity_id,
+                    effort.timestamp.format("%Y-%m-%d")
+                );
+            }
+        }
 
-        Ok(())
+        println!();
+        match &report.critical_power {
+            Some(fit) => println!(
+                "Critical power: {:.0} W{}, W': {:.1} kJ ({} efforts)",
+                fit.critical,
+                Self::format_watts_per_kg(fit.critical, report.weight),
+                fit.reserve / 1000.0,
+                fit.efforts
+            ),
+            None => println!("Critical power: N/A (needs 2-20 minute power efforts)"),
+        }
+        if let Some(ftp) = report.ftp_estimate {
+            println!(
+                "Estimated FTP: {:.0} W{}",
+                ftp,
+                Self::format_watts_per_kg(ftp, report.weight)
+            );
+        }
+        match &report.critical_speed {
+            Some(fit) => println!(
+                "Critical speed: {} ({:.2} m/s), D': {:.0} m ({} efforts)",
+                Self::format_pace(fit.critical),
+                fit.critical,
+                fit.reserve,
+                fit.efforts
+            ),
+            None => println!("Critical speed: N/A (needs 2-20 minute running efforts)"),
+        }
     }
 
-    fn fetch_training_workouts(db: &Database) -> anyhow::Result<Vec<TrainingWorkout>> {
-        let mut stmt = db.connection().prepare("SELECT sport, avg_hr, avg_power, elevation, duration, distance, rpe FROM activities WHERE sport IS 'running' AND rpe IS NOT NULL")?;
+    // best effort per kind and target, from one activity or all activities of the period
+    fn fetch_best_efforts(
+        db: &Database,
+        args: &PowerCurveArgs,
+    ) -> anyhow::Result<Vec<EffortEntry>> {
+        let mut condition = match args.id {
+            Some(id) => Condition::new("activities.id = ?", vec![Value::Integer(id)]),
+            None => Condition::period(&args.period),
+        };
+        if let Some(sports) = &args.activity {
+            condition = condition.and(Condition::any_of(Field::Sport, sports));
+        }
 
-        let workout_iter = stmt.query_map([], |row| {
-            Ok(TrainingWorkout {
-                avg_hr: row.get(1)?,
-                avg_power: row.get(2)?,
-                elevation: row.get(3)?,
-                duration: row.get(4)?,
-                distance: row.get(5)?,
-                rpe: row.get(6)?,
+        // SQLite takes the bare activity columns from the row holding the MAX value
+        let query = format!(
+            "SELECT kind, target, MAX(value), activities.id, activities.timestamp, activities.utc_offset FROM best_efforts JOIN activities ON activities.id = best_efforts.activity_id WHERE {} GROUP BY kind, target",
+            condition.sql
+        );
+
+        let mut stmt = db.connection().prepare(&query)?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(&condition.values), |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, f64>(1)?,
+                    row.get::<_, f64>(2)?,
+                    row.get::<_, i64>(3)?,
+                    timestamps::activity_time(row, 4, 5)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut efforts: Vec<EffortEntry> = rows
+            .into_iter()
+            .filter_map(|(kind, target, value, activity_id, timestamp)| {
+                Some(EffortEntry {
+                    kind: EffortKind::from_name(&kind)?,
+                    target,
+                    value,
+                    activity_id,
+                    timestamp,
+                })
             })
-        })?;
+            .collect();
+        efforts.sort_by(|a, b| {
+            (a.kind as u8)
+                .cmp(&(b.kind as u8))
+                .then(a.target.total_cmp(&b.target))
+        });
 
-        let workouts: Vec<TrainingWorkout> =
-            workout_iter.collect::<Result<Vec<TrainingWorkout>, rusqlite::Error>>()?;
-        Ok(workouts)
+        Ok(efforts)
     }
 
-    fn prepare_data(workouts: &[TrainingWorkout]) -> (Vec<Vec<f64>>, Vec<f64>) {
-        let features: Vec<Vec<f64>> = workouts
-            .iter()
-            .map(|w| vec![w.avg_hr, w.avg_power, w.elevation, w.duration, w.distance])
-            .collect();
+    // 5s, 1m, 1h
+    fn format_effort_duration(seconds: f64) -> String {
+        let seconds = seconds as u64;
+        if seconds >= 3600 && seconds.is_multiple_of(3600) {
+            format!("{}h", seconds / 3600)
+        } else if seconds >= 60 && seconds.is_multiple_of(60) {
+            format!("{}m", seconds / 60)
+        } else {
+            format!("{}s", seconds)
+        }
+    }
+
+    // " (X.XX W/kg)" when the weight is known
+    fn format_watts_per_kg(power: f64, weight: Option<f64>) -> String {
+        weight
+            .map(|weight| format!(" ({:.2} W/kg)", power / weight))
+            .unwrap_or_default()
+    }
+
+    // m/s as M:SS per km
+    fn format_pace(speed: f64) -> String {
+        if speed <= 0.0 {
+            return "N/A".to_string();
+        }
+        format!("{}/km", athlete::format_pace(1000.0 / speed))
+    }
 
-        let targets: Vec<f64> = workouts.iter().map(|w| w.rpe).collect();
+    // m:ss, or h:mm:ss for an hour or more
+    fn format_clock(seconds: f64) -> String {
+        let seconds = seconds.round() as u64;
+        let (hours, mins, secs) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+        if hours > 0 {
+            format!("{}:{:02}:{:02}", hours, mins, secs)
+        } else {
+            format!("{}:{:02}", mins, secs)
+        }
+    }
+
+    fn run_zones(config: &Config, db: &Database) -> anyhow::Result<()> {
+        let tx = db.connection().unchecked_transaction()?;
+        let activities = {
+            let mut stmt = tx.prepare(
+                "SELECT id, timestamp, utc_offset, sport FROM activities WHERE id IN (SELECT activity_id FROM streams)",
+            )?;
+            stmt.query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    timestamps::activity_time(row, 1, 2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?
+        };
+
+        for (id, timestamp, sport) in &activities {
+            let Some(stream) = Stream::load(&tx, *id)? else {
+                continue;
+            };
+            let date = timestamp.date_naive();
+            let athlete = body::athlete_on(config, &tx, date)?;
+            let zones = TimeInZones::compute(&stream, sport, &athlete);
+            TimeInZones::save_all(&tx, *id, &zones)?;
+        }
+        tx.commit()?;
 
-        (features, targets)
+        println!(
+            "Calculated time in zones for {} activities.",
+            activities.len()
+        );
+        Ok(())
     }
 
-    fn scale_features(features: &[Vec<f64>]) -> (Vec<Vec<f64>>, Vec<(f64, f64)>) {
-        let num_features = features[0].len();
+    fn print_load(days: &[DailyLoad], skipped: usize, method: LoadMethod) {
+        let Some(today) = days.last() else {
+            println!("No activities with enough data to calculate training load.");
+            return;
+        };
+
+        println!(
+            "{:<12} {:>7} {:>7} {:>7} {:>7} {:>7} {:>6}  Warnings",
+            "Date", "Load", "ATL", "CTL", "TSB", "Ramp", "ACWR"
+        );
+        for day in days {
+            println!(
+                "{:<12} {:>7.1} {:>7.1} {:>7.1} {:>7.1} {:>7.1} {:>6}  {}",
+                day.date.format("%Y-%m-%d").to_string(),
+                day.load,
+                day.atl,
+                day.ctl,
+                day.tsb,
+                day.ramp,
+                Self::format_optional(day.acwr, 2),
+                day.warnings.as_deref().unwrap_or("")
+            );
+        }
 
-        // Calculate min and max for each feature
-        let mut min_max = vec![(f64::INFINITY, f64::NEG_INFINITY); num_features];
+        println!(
+            "\nFitness (CTL): {:.1}, Fatigue (ATL): {:.1}, Form (TSB): {:.1}",
+            today.ctl, today.atl, today.tsb
+        );
+        println!(
+            "Ramp rate: {:.1}/week, ACWR: {}",
+            today.ramp,
+            Self::format_optional(today.acwr, 2)
+        );
+        if let Some(warnings) = &today.warnings {
+            println!("Warning: {}", warnings);
+        }
+        if skipped > 0 {
+            println!(
+                "\nSkipped {} activities without the data needed for the {} load method.",
+                skipped,
+                method.name()
+            );
+        }
+    }
+
+    fn fetch_load_inputs(db: &Database) -> anyhow::Result<Vec<LoadInput>> {
+        let mut stmt = db.connection().prepare(
+            "SELECT id, timestamp, utc_offset, sport, duration, NULLIF(avg_hr, 0), avg_power, rpe FROM activities ORDER BY timestamp",
+        )?;
 
-        for sample in features {
-            for (i, &val) in sample.iter().enumerate() {
-                min_max[i].0 = min_max[i].0.min(val);
-                min_max[i].1 = min_max[i].1.max(val);
+        let inputs = stmt
+            .query_map([], |row| {
+                Ok(LoadInput {
+                    id: row.get(0)?,
+                    timestamp: timestamps::activity_time(row, 1, 2)?,
+                    sport: row.get(3)?,
+                    duration: row.get::<_, Option<f64>>(4)?.unwrap_or(0.0),
+                    avg_hr: row.get(5)?,
+                    avg_power: row.get(6)?,
+                    rpe: row.get(7)?,
+                })
+            })?
+            .collect::<Result<Vec<LoadInput>, rusqlite::Error>>()?;
+
+        Ok(inputs)
+    }
+
+    fn train_models(samples: &[Sample]) -> anyhow::Result<RpeModels> {
+        let sports: BTreeSet<&str> = samples.iter().map(|s| s.sport.as_str()).collect();
+
+        let mut models = RpeModels::default();
+        for sport in sports {
+            if let Some(model) = RpeModel::train(sport, samples)? {
+                models.models.push(model);
             }
         }
 
-        // Scale features
-        let scaled_features: Vec<Vec<f64>> = features
-            .iter()
-            .map(|sample| {
-                sample
-                    .iter()
-                    .enumerate()
-                    .map(|(i, &val)| {
-                        let (min_val, max_val) = min_max[i];
-                        if max_val > min_val {
-                            (val - min_val) / (max_val - min_val)
-                        } else {
-                            0.0
-                        }
-                    })
-                    .collect()
-            })
-            .collect();
+        Ok(models)
+    }
 
-        (scaled_features, min_max)
+    fn print_models(models: &[RpeModel], retrained: bool) {
+        println!(
+            "{} RPE models:\n",
+            if retrained { "Trained" } else { "Saved" }
+        );
+        println!(
+            "{:<15} {:<8} {:<8} {:<8} Features",
+            "Sport", "Samples", "MAE", "R²"
+        );
+        for model in models {
+            let features = model
+                .features
+                .iter()
+                .map(|f| f.name())
+                .collect::<Vec<_>>()
+                .join(", ");
+            println!(
+                "{:<15} {:<8} {:<8} {:<8} {}",
+                model.sport,
+                model.samples,
+                Self::format_optional(model.mae, 2),
+                Self::format_optional(model.r2, 2),
+                features
+            );
+        }
     }
 
-    fn fetch_workouts(db: &Database) -> anyhow::Result<Vec<Workout>> {
-        let mut stmt = db.connection().prepare("SELECT sport, avg_hr, avg_power, elevation, duration, distance, rpe, rpe_est FROM activities WHERE sport IS 'running' AND avg_power IS NOT NULL AND rpe IS NULL AND rpe_est IS false")?;
+    fn format_optional(value: Option<f64>, precision: usize) -> String {
+        match value {
+            Some(value) => format!("{:.*}", precision, value),
+            None => "N/A".to_string(),
+        }
+    }
 
-        let workout_iter = stmt.query_map([], |row| {
-            Ok(Workout {
-                avg_hr: row.get(1)?,
-                avg_power: row.get(2)?,
-                elevation: row.get(3)?,
-                duration: row.get(4)?,
-                distance: row.get(5)?,
-            })
-        })?;
-
-        let workouts: Vec<Workout> =
-            workout_iter.collect::<Result<Vec<Workout>, rusqlite::Error>>()?;
-        Ok(workouts)
-    }
-
-    fn estimate_rpe(
-        workout: &Workout,
-        scaling_params: &Vec<(f64, f64)>,
-        model: &FittedLinearRegression<f64>,
-    ) -> anyhow::Result<ArrayBase<OwnedRepr<f64>, Dim<[usize; 1]>>> {
-        let new_workout = vec![
-            workout.avg_hr,
-            workout.avg_power,
-            workout.elevation,
-            workout.duration,
-            workout.distance,
-        ];
-
-        // Scale new workout
-        let scaled_new_workout: Vec<f64> = new_workout
-            .iter()
-            .enumerate()
-            .map(|(i, &val)| {
-                let (min_val, max_val) = scaling_params[i];
-                if max_val > min_val {
-                    (val - min_val) / (max_val - min_val)
-                } else {
-                    0.0
-                }
-            })
-            .collect();
+    fn fetch_samples(db: &Database) -> anyhow::Result<Vec<Sample>> {
+        // missing heart rate and duration are stored as 0, missing ascent as NULL
+        let mut stmt = db.connection().prepare(
+            "SELECT id, sport, NULLIF(avg_hr, 0), avg_power, COALESCE(elevation, 0), NULLIF(duration, 0), distance, rpe, rpe_est FROM activities",
+        )?;
 
-        // Convert scaled new workout to 2D ndarray
-        let scaled_new_workout_array =
-            Array2::from_shape_vec((1, scaled_new_workout.len()), scaled_new_workout)?;
+        let samples = stmt
+            .query_map([], |row| {
+                Ok(Sample {
+                    id: row.get(0)?,
+                    sport: row.get(1)?,
+                    avg_hr: row.get(2)?,
+                    avg_power: row.get(3)?,
+                    elevation: row.get(4)?,
+                    duration: row.get(5)?,
+                    distance: row.get(6)?,
+                    rpe: row.get(7)?,
+                    rpe_est: row.get::<_, Option<bool>>(8)?.unwrap_or(false),
+                })
+            })?
+            .collect::<Result<Vec<Sample>, rusqlite::Error>>()?;
+
+        Ok(samples)
+    }
 
-        Ok(model.predict(&scaled_new_workout_array))
+    // store estimates as RPE values flagged as estimated, replacing earlier estimates
+    fn save_estimates(db: &Database, estimates: &[RpeEstimate]) -> anyhow::Result<()> {
+        let tx = db.connection().unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "UPDATE activities SET rpe = ?1, rpe_est = true WHERE id = ?2 AND (rpe IS NULL OR rpe_est IS true)",
+            )?;
+            for estimate in estimates {
+                stmt.execute(params![estimate.estimated_rpe, estimate.id])?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(())
     }
 }
@@ -1,5 +1,11 @@
+use crate::athlete::{Athlete, AthleteKey};
+use crate::body;
 use crate::config::Config;
+use crate::db::Database;
+use crate::output::OutputFormat;
+use chrono::{Local, NaiveDate};
 use clap::{Args, Subcommand};
+use serde::Serialize;
 use std::path::PathBuf;
 
 #[derive(Debug, Args)]
@@ -44,6 +50,23 @@ pub enum ConfigKey {
     #[command(name = "data")]
     #[command(about = "get the data directory")]
     Data,
+
+    #[command(name = "records")]
+    #[command(about = "get whether per-record time series are imported")]
+    Records,
+
+    #[command(name = "athlete")]
+    #[command(about = "get athlete profile values")]
+    Athlete {
+        #[arg(value_enum, help = "athlete key, all keys if omitted")]
+        key: Option<AthleteKey>,
+
+        #[arg(
+            long,
+            help = "date (YYYY-MM-DD) the value applies to, defaults to today"
+        )]
+        date: Option<NaiveDate>,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -51,36 +74,182 @@ pub enum SetConfigKey {
     #[command(name = "data")]
     #[command(about = "set the data directory")]
     Data { path: PathBuf },
+
+    #[command(name = "records")]
+    #[command(about = "enable or disable importing per-record time series")]
+    Records {
+        #[arg(action = clap::ArgAction::Set, help = "true or false")]
+        enabled: bool,
+    },
+
+    #[command(name = "athlete")]
+    #[command(about = "set an athlete profile value")]
+    Athlete {
+        #[arg(value_enum, help = "athlete key")]
+        key: AthleteKey,
+
+        #[arg(help = "value; paces as M:SS per km, zones as comma separated boundaries")]
+        value: String,
+
+        #[arg(
+            long,
+            help = "date (YYYY-MM-DD) from which the value applies, all dates if omitted"
+        )]
+        since: Option<NaiveDate>,
+    },
+}
+
+// one config value, athlete values with the date they apply from
+#[derive(Debug, Serialize)]
+struct Setting {
+    key: String,
+    value: Option<String>,
+    since: Option<NaiveDate>,
+}
+
+impl Setting {
+    fn new(key: &str, value: impl ToString) -> Self {
+        Setting {
+            key: key.to_string(),
+            value: Some(value.to_string()),
+            since: None,
+        }
+    }
 }
 
 impl ConfigArgs {
-    pub fn run(&self) -> anyhow::Result<()> {
+    pub fn run(&self, format: OutputFormat) -> anyhow::Result<()> {
         match &self.actions {
             Actions::Path => {
-                println!("{}", Config::get_config_file_path().display());
+                let path = Config::get_config_file_path();
+                format.print_rows(&[Setting::new("path", path.display())], || {
+                    println!("{}", path.display());
+                    Ok(())
+                })?;
             }
             Actions::Show => {
                 let config = Config::load_or_create()?;
-                println!("data = {:?}", config.get_data_path());
+                format.print_document(&config, &Self::settings(&config), || {
+                    print!("{}", toml::to_string_pretty(&config)?);
+                    Ok(())
+                })?;
             }
             Actions::Get(args) => {
                 let config = Config::load_or_create()?;
-                match args.key {
-                    ConfigKey::Data => println!("{}", config.get_data_path().display()),
+                match &args.key {
+                    ConfigKey::Data => {
+                        let data = config.get_data_path();
+                        format.print_rows(&[Setting::new("data", data.display())], || {
+                            println!("{}", data.display());
+                            Ok(())
+                        })?;
+                    }
+                    ConfigKey::Records => {
+                        let records = config.get_import_records();
+                        format.print_rows(&[Setting::new("records", records)], || {
+                            println!("{}", records);
+                            Ok(())
+                        })?;
+                    }
+                    ConfigKey::Athlete { key, date } => {
+                        let date = date.unwrap_or_else(|| Local::now().date_naive());
+                        let athlete = Self::athlete_on(&config, date)?;
+                        let settings: Vec<Setting> = match key {
+                            // an unset key is still listed, without a value
+                            Some(key) => vec![Setting {
+                                key: key.name().to_string(),
+                                value: athlete.get(*key),
+                                since: None,
+                            }],
+                            None => AthleteKey::ALL
+                                .into_iter()
+                                .filter_map(|key| Some(Setting::new(key.name(), athlete.get(key)?)))
+                                .collect(),
+                        };
+                        format.print_rows(&settings, || {
+                            match key {
+                                Some(key) => match &settings[0].value {
+                                    Some(value) => println!("{}", value),
+                                    None => println!("{} is not set", key.name()),
+                                },
+                                None => {
+                                    for setting in &settings {
+                                        if let Some(value) = &setting.value {
+                                            println!("{} = {}", setting.key, value);
+                                        }
+                                    }
+                                }
+                            }
+                            Ok(())
+                        })?;
+                    }
                 }
             }
             Actions::Set(args) => {
                 let mut config = Config::load_or_create()?;
-                match &args.key {
+                let setting = match &args.key {
                     SetConfigKey::Data { path } => {
                         config.set_data_path(path.to_owned());
                         config.save()?;
-                        println!("Set data to {}", config.get_data_path().display());
+                        Setting::new("data", config.get_data_path().display())
                     }
-                }
+                    SetConfigKey::Records { enabled } => {
+                        config.set_import_records(*enabled);
+                        config.save()?;
+                        Setting::new("records", config.get_import_records())
+                    }
+                    SetConfigKey::Athlete { key, value, since } => {
+                        config.athlete_entry_mut(*since).set(*key, value)?;
+                        config.save()?;
+                        Setting {
+                            since: *since,
+                            ..Setting::new(key.name(), value)
+                        }
+                    }
+                };
+                format.print_rows(std::slice::from_ref(&setting), || {
+                    let value = setting.value.as_deref().unwrap_or_default();
+                    match setting.since {
+                        Some(since) => println!("Set {} to {} from {}", setting.key, value, since),
+                        None => println!("Set {} to {}", setting.key, value),
+                    }
+                    Ok(())
+                })?;
             }
         }
 
         Ok(())
     }
+
+    // every value of the config file, athlete values once per entry that sets them
+    fn settings(config: &Config) -> Vec<Setting> {
+        let mut settings = vec![
+            Setting::new("data", config.get_data_path().display()),
+            Setting::new("records", config.get_import_records()),
+        ];
+        for entry in config.get_athlete_entries() {
+            for key in AthleteKey::ALL {
+                if let Some(value) = entry.get(key) {
+                    settings.push(Setting {
+                        since: entry.since,
+                        ..Setting::new(key.name(), value)
+                    });
+                }
+            }
+        }
+        settings
+    }
+
+    // the configured profile with the measured weight; the database is only read, so it is
+    // skipped unless its schema is current
+    fn athlete_on(config: &Config, date: NaiveDate) -> anyhow::Result<Athlete> {
+        if !Database::path(config).exists() {
+            return Ok(config.get_athlete(date));
+        }
+        let db = Database::new(config)?;
+        if !db.initialized()? || !db.is_current()? {
+            return Ok(config.get_athlete(date));
+        }
+        body::athlete_on(config, db.connection(), date)
+    }
 }
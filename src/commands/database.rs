@@ -1,17 +1,31 @@
+use crate::VERSION;
+use crate::body;
 use crate::config::Config;
-use crate::db::Database;
-use crate::models::{Activity, Device, File};
+use crate::db::{Database, REIMPORT_MESSAGE};
+use crate::efforts::BestEffort;
+use crate::models::{Activity, BodyMetrics, Device, File, FitData, Lap, Length, Record, Set};
+use crate::output::OutputFormat;
+use crate::streams::Stream;
+use crate::timestamps;
+use crate::wellness::{WellnessDay, WellnessSamples};
+use crate::zones::TimeInZones;
 use anyhow::anyhow;
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, TimeZone};
 use clap::{Args, Subcommand};
 use fitparser::Value;
 use fitparser::de::{DecodeOption, FitObject, FitStreamProcessor};
 use fitparser::profile::MesgNum;
 use indicatif::ProgressBar;
 use rusqlite::{Connection, params};
-use std::collections::HashSet;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use std::process;
-use std::time::{Duration, Instant};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, PoisonError, mpsc};
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
 
 #[derive(Debug, Args)]
 pub struct DatabaseArgs {
@@ -23,32 +37,125 @@ pub struct DatabaseArgs {
 pub enum Actions {
     #[command(name = "import")]
     #[command(about = "import new .fit files into database")]
-    Import,
+    Import(ImportArgs),
 
     #[command(name = "recreate")]
     #[command(about = "recreate database from all .fit files")]
-    Recreate,
+    Recreate(DecodeArgs),
+
+    #[command(name = "errors")]
+    #[command(about = "list .fit files that failed to import")]
+    Errors,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    #[command(flatten)]
+    pub decode: DecodeArgs,
+
+    #[arg(long, help = "retry files that failed to import before")]
+    pub retry_failed: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct DecodeArgs {
+    #[arg(
+        short,
+        long,
+        value_parser = clap::value_parser!(u16).range(1..),
+        help = "number of threads decoding .fit files, defaults to the number of CPU cores"
+    )]
+    pub jobs: Option<u16>,
+}
+
+impl DecodeArgs {
+    fn jobs(&self) -> usize {
+        self.jobs.map(usize::from).unwrap_or_else(|| {
+            thread::available_parallelism()
+                .map(|jobs| jobs.get())
+                .unwrap_or(1)
+        })
+    }
+}
+
+// a file to import, replacing the rows of an earlier version of it if it changed
+struct PendingFile {
+    file: File,
+    replaces: Option<i64>,
+}
+
+// file row in the database
+struct StoredFile {
+    id: i64,
+    path: String,
+    hash: String,
+    size: i64,
+    mtime: i64,
+    // same contents as an imported file, so nothing was imported from it
+    duplicate: bool,
+}
+
+// what an import has to do after comparing the data directory with the files table
+struct ImportPlan {
+    pending: Vec<PendingFile>,
+    renamed: usize,
+    removed: usize,
+    duplicates: usize,
+}
+
+// decoded file sent from a decoding thread to the writer
+struct DecodedFile {
+    index: usize,
+    data: anyhow::Result<FitData>,
+    decode_time: Duration,
+}
+
+// files the writer has written so far; decoding threads stay within `window` files of it, so
+// files decoded out of order cannot pile up in memory behind a slow one
+struct WriteProgress {
+    written: Mutex<usize>,
+    advanced: Condvar,
+    window: usize,
+}
+
+// lets waiting decoding threads go when the writer stops, also after an error
+struct ReleaseDecoders<'a>(&'a WriteProgress);
+
+#[derive(Debug, Serialize)]
+pub struct ImportError {
+    pub path: String,
+    pub filename: String,
+    pub error: String,
+    // app version that failed to import the file
+    pub app_version: String,
+    pub timestamp: DateTime<Local>,
 }
 
 impl DatabaseArgs {
-    pub fn run(&self, config: &Config, db: &Database) -> anyhow::Result<()> {
+    pub fn run(&self, config: &Config, db: &Database, format: OutputFormat) -> anyhow::Result<()> {
         match &self.actions {
-            Actions::Import => {
-                Self::run_import(config, db)?;
+            Actions::Import(args) => {
+                Self::run_import(config, db, args.decode.jobs(), args.retry_failed)?;
+            }
+            Actions::Recreate(args) => {
+                self.run_recreate(config, db, args.jobs())?;
             }
-            Actions::Recreate => {
-                Self::run_recreate(&self, config, db)?;
+            Actions::Errors => {
+                Self::run_errors(db, format)?;
             }
         }
         Ok(())
     }
 
-    fn run_import(config: &Config, db: &Database) -> anyhow::Result<()> {
+    fn run_import(
+        config: &Config,
+        db: &Database,
+        jobs: usize,
+        retry_failed: bool,
+    ) -> anyhow::Result<()> {
         // do not allow import if database is invalid
         if !db.get_db_validitiy() {
-            println!(
-                "The database version does not match the app version.\nPlease run 'queryfit database recreate'.\nNo data will be lost."
-            );
+            println!("{}", REIMPORT_MESSAGE);
             process::exit(0);
         }
 
@@ -57,47 +164,137 @@ impl DatabaseArgs {
         let files: Vec<_> = walkdir::WalkDir::new(data_path)
             .into_iter()
             .filter_map(|e| e.ok())
-            .filter(|e| e.path().extension().map_or(false, |ext| ext == "fit"))
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "fit"))
             .collect();
 
+        let import_records = config.get_import_records();
+
         let pb = ProgressBar::new(files.len() as u64);
 
         println!("Adding .fit file data to database...");
-        let tx = db.connection().unchecked_transaction()?;
+        let mut tx = db.connection().unchecked_transaction()?;
         let profile_import = std::env::var_os("QUERYFIT_PROFILE_IMPORT").is_some();
         let import_started = Instant::now();
+
+        let plan = Self::plan_import(&files, &tx, retry_failed, &pb)?;
+        let pending = plan.pending;
+
+        let jobs = jobs.min(pending.len()).max(1);
         let mut total_parse_time = Duration::ZERO;
         let mut total_insert_time = Duration::ZERO;
-        // TODO: process multiple files in parallel?
-        for item in files {
-            let fit_file_path = item.path();
-            let file = File::new(Self::get_filename(&fit_file_path)?);
+        let (mut added, mut changed, mut failed) = (0, 0, 0);
 
-            // move to next file if it exists in database
-            if Self::check_file_imported(&file, &tx)? {
-                pb.inc(1);
-                continue;
+        // decode files on `jobs` threads while this thread writes them to the database in
+        // file order, so activity ids do not depend on which file finished decoding first
+        let next_file = AtomicUsize::new(0);
+        let progress = WriteProgress::new(jobs * 4);
+        thread::scope(|scope| -> anyhow::Result<()> {
+            let (sender, receiver) = mpsc::sync_channel::<DecodedFile>(jobs * 2);
+
+            for _ in 0..jobs {
+                let sender = sender.clone();
+                let (next_file, pending, progress) = (&next_file, &pending, &progress);
+                scope.spawn(move || {
+                    loop {
+                        let index = next_file.fetch_add(1, Ordering::Relaxed);
+                        let Some(pending_file) = pending.get(index) else {
+                            break;
+                        };
+                        progress.wait_for(index);
+
+                        let parse_started = Instant::now();
+                        let data =
+                            Self::read_activity(Path::new(&pending_file.file.path), import_records);
+                        let decoded = DecodedFile {
+                            index,
+                            data,
+                            decode_time: parse_started.elapsed(),
+                        };
+                        // the writer stopped after an error
+                        if sender.send(decoded).is_err() {
+                            break;
+                        }
+                    }
+                });
             }
+            drop(sender);
+            let _release = ReleaseDecoders(&progress);
 
-            let parse_started = Instant::now();
-            let activity_data = Self::read_activity(&fit_file_path)?;
-            total_parse_time += parse_started.elapsed();
+            let mut decoded: HashMap<usize, DecodedFile> = HashMap::new();
+            let mut next_insert = 0;
+            for file in receiver {
+                decoded.insert(file.index, file);
 
-            let insert_started = Instant::now();
-            Self::add_activity(activity_data, &tx)?;
-            Self::add_filename(&file, &tx)?;
-            total_insert_time += insert_started.elapsed();
+                while let Some(file) = decoded.remove(&next_insert) {
+                    total_parse_time += file.decode_time;
+                    let pending_file = &pending[next_insert];
 
-            pb.inc(1);
-        }
+                    // a failing file only rolls back its own rows, and keeps the rows of the
+                    // version it was meant to replace
+                    let insert_started = Instant::now();
+                    let savepoint = tx.savepoint()?;
+                    let result = file.data.and_then(|data| {
+                        if let Some(file_id) = pending_file.replaces {
+                            Self::remove_file(file_id, &savepoint)?;
+                        }
+                        let file_id = Self::add_file(&pending_file.file, &savepoint)?;
+                        Self::add_activity(data, file_id, config, &savepoint)
+                    });
+                    match result {
+                        Ok(()) => {
+                            savepoint.commit()?;
+                            Self::clear_import_error(&pending_file.file, &tx)?;
+                            match pending_file.replaces {
+                                Some(_) => changed += 1,
+                                None => added += 1,
+                            }
+                        }
+                        Err(err) => {
+                            drop(savepoint);
+                            Self::add_import_error(&pending_file.file, &err, &tx)?;
+                            failed += 1;
+                        }
+                    }
+                    total_insert_time += insert_started.elapsed();
+
+                    next_insert += 1;
+                    progress.advance(next_insert);
+                    pb.inc(1);
+                }
+            }
+
+            Ok(())
+        })?;
         tx.commit()?;
         pb.finish_and_clear();
         if profile_import {
+            // fit_decode is summed over all decoding threads
             println!(
-                "import timing: total={:.2?}, fit_decode={:.2?}, db_insert={:.2?}",
+                "import timing: total={:.2?}, fit_decode={:.2?}, db_insert={:.2?}, jobs={}",
                 import_started.elapsed(),
                 total_parse_time,
-                total_insert_time
+                total_insert_time,
+                jobs
+            );
+        }
+        let summary: Vec<String> = [
+            (added, "new"),
+            (changed, "changed"),
+            (plan.renamed, "renamed"),
+            (plan.removed, "removed"),
+            (plan.duplicates, "duplicate(s) skipped"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, label)| format!("{} {}", count, label))
+        .collect();
+        if !summary.is_empty() {
+            println!("Files: {}.", summary.join(", "));
+        }
+        if failed > 0 {
+            println!(
+                "{} file(s) failed to import and were skipped, see 'queryfit database errors'.",
+                failed
             );
         }
         println!("done.");
@@ -105,15 +302,218 @@ impl DatabaseArgs {
         Ok(())
     }
 
-    pub fn run_recreate(&self, config: &Config, db: &Database) -> anyhow::Result<()> {
+    pub fn run_recreate(&self, config: &Config, db: &Database, jobs: usize) -> anyhow::Result<()> {
         db.reset()?;
         db.init_database()?;
         db.set_db_valid();
-        Self::run_import(config, db)?;
+        Self::run_import(config, db, jobs, true)?;
         Ok(())
     }
 
-    fn read_activity(path: &Path) -> anyhow::Result<(Vec<Activity>, Vec<Device>)> {
+    fn run_errors(db: &Database, format: OutputFormat) -> anyhow::Result<()> {
+        let errors = Self::get_import_errors(db)?;
+
+        format.print_rows(&errors, || {
+            if errors.is_empty() {
+                println!("No import errors.");
+                return Ok(());
+            }
+            for error in &errors {
+                println!(
+                    "{}  {} (v{})\n  {}\n  {}",
+                    error.timestamp.format("%Y-%m-%d %H:%M"),
+                    error.filename,
+                    error.app_version.trim_start_matches('v'),
+                    error.path,
+                    error.error
+                );
+            }
+            println!(
+                "\n{} file(s) failed to import. Run 'queryfit database import --retry-failed' to retry them.",
+                errors.len()
+            );
+            Ok(())
+        })
+    }
+
+    fn get_import_errors(db: &Database) -> anyhow::Result<Vec<ImportError>> {
+        let mut stmt = db.connection().prepare(
+            "SELECT path, filename, error, app_version, timestamp FROM import_errors ORDER BY path",
+        )?;
+
+        let errors = stmt
+            .query_map(params![], |row| {
+                Ok(ImportError {
+                    path: row.get(0)?,
+                    filename: row.get(1)?,
+                    error: row.get(2)?,
+                    app_version: row.get(3)?,
+                    timestamp: timestamps::local_time(row, 4)?,
+                })
+            })?
+            .collect::<Result<Vec<ImportError>, _>>()?;
+
+        Ok(errors)
+    }
+
+    // compare the .fit files in the data directory with the files table: unchanged files are
+    // skipped without hashing, moved files keep their rows, deleted files lose them, and files
+    // with the same contents as another one are only imported once
+    fn plan_import(
+        files: &[walkdir::DirEntry],
+        conn: &Connection,
+        retry_failed: bool,
+        pb: &ProgressBar,
+    ) -> anyhow::Result<ImportPlan> {
+        let mut stored: HashMap<String, StoredFile> = Self::get_stored_files(conn)?
+            .into_iter()
+            .map(|file| (file.path.clone(), file))
+            .collect();
+        let on_disk: HashSet<String> = files
+            .iter()
+            .map(|entry| entry.path().to_string_lossy().into_owned())
+            .collect();
+
+        // files whose path is gone may have been renamed, the rest have to stay unique;
+        // duplicates have no rows of their own, so they are neither unique nor renamed
+        let mut moved: HashMap<String, Vec<i64>> = HashMap::new();
+        let mut hashes: HashSet<String> = HashSet::new();
+        for file in stored.values().filter(|file| !file.duplicate) {
+            if on_disk.contains(&file.path) {
+                hashes.insert(file.hash.clone());
+            } else {
+                moved.entry(file.hash.clone()).or_default().push(file.id);
+            }
+        }
+
+        let mut plan = ImportPlan {
+            pending: Vec::new(),
+            renamed: 0,
+            removed: 0,
+            duplicates: 0,
+        };
+        // known duplicates stay skipped as long as a file with their contents is imported
+        let mut known_duplicates: Vec<(i64, File)> = Vec::new();
+        for entry in files {
+            let path = entry.path();
+            let path_string = path.to_string_lossy().into_owned();
+            let metadata = entry.metadata()?;
+            let size = metadata.len() as i64;
+            let mtime = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |time| time.as_secs() as i64);
+
+            let mut known = stored.remove(&path_string);
+            let unchanged = known
+                .as_ref()
+                .is_some_and(|file| file.size == size && file.mtime == mtime);
+            if let Some(duplicate) = known.take_if(|file| unchanged && file.duplicate) {
+                let file = File {
+                    filename: Self::get_filename(path)?,
+                    path: path_string,
+                    hash: duplicate.hash,
+                    size,
+                    mtime,
+                };
+                known_duplicates.push((duplicate.id, file));
+                continue;
+            }
+            if unchanged
+                || (!retry_failed && Self::check_file_failed(&path_string, size, mtime, conn)?)
+            {
+                pb.inc(1);
+                continue;
+            }
+
+            let file = File {
+                filename: Self::get_filename(path)?,
+                path: path_string,
+                hash: Self::hash_file(path)?,
+                size,
+                mtime,
+            };
+
+            // a duplicate with other contents now is a new file
+            if let Some(duplicate) = known.take_if(|file| file.duplicate) {
+                if duplicate.hash == file.hash {
+                    Self::update_file(duplicate.id, &file, conn)?;
+                    known_duplicates.push((duplicate.id, file));
+                    continue;
+                }
+                Self::remove_file(duplicate.id, conn)?;
+            }
+
+            match known {
+                // touched, but the contents are the same
+                Some(known) if known.hash == file.hash => {
+                    Self::update_file(known.id, &file, conn)?;
+                    pb.inc(1);
+                }
+                Some(known) => {
+                    hashes.remove(&known.hash);
+                    hashes.insert(file.hash.clone());
+                    plan.pending.push(PendingFile {
+                        file,
+                        replaces: Some(known.id),
+                    });
+                }
+                None => {
+                    if let Some(id) = moved.get_mut(&file.hash).and_then(|ids| ids.pop()) {
+                        Self::update_file(id, &file, conn)?;
+                        hashes.insert(file.hash);
+                        plan.renamed += 1;
+                        pb.inc(1);
+                    } else if !hashes.insert(file.hash.clone()) {
+                        Self::add_duplicate(&file, conn)?;
+                        plan.duplicates += 1;
+                        pb.inc(1);
+                    } else {
+                        plan.pending.push(PendingFile {
+                            file,
+                            replaces: None,
+                        });
+                    }
+                }
+            }
+        }
+
+        // a duplicate of a renamed file stays one, a duplicate of a deleted file takes its
+        // place, and a duplicate of a changed file has its contents imported
+        for (id, file) in known_duplicates {
+            if hashes.contains(&file.hash) {
+                pb.inc(1);
+                continue;
+            }
+            Self::remove_file(id, conn)?;
+            if let Some(moved_id) = moved.get_mut(&file.hash).and_then(|ids| ids.pop()) {
+                Self::update_file(moved_id, &file, conn)?;
+                hashes.insert(file.hash);
+                plan.renamed += 1;
+                pb.inc(1);
+            } else {
+                hashes.insert(file.hash.clone());
+                plan.pending.push(PendingFile {
+                    file,
+                    replaces: None,
+                });
+            }
+        }
+
+        // duplicates that are gone had nothing imported
+        for file in stored.values().filter(|file| file.duplicate) {
+            Self::remove_file(file.id, conn)?;
+        }
+        for id in moved.into_values().flatten() {
+            Self::remove_file(id, conn)?;
+            plan.removed += 1;
+        }
+
+        Ok(plan)
+    }
+
+    fn read_activity(path: &Path, import_records: bool) -> anyhow::Result<FitData> {
         let opts: HashSet<DecodeOption> = HashSet::from([
             DecodeOption::SkipHeaderCrcValidation,
             DecodeOption::SkipDataCrcValidation,
@@ -133,6 +533,16 @@ impl DatabaseArgs {
         let mut devices: Vec<Device> = Vec::new();
         let mut curr_device = Device::new();
 
+        let mut laps: Vec<Lap> = Vec::new();
+        let mut sets: Vec<Set> = Vec::new();
+        let mut lengths: Vec<Length> = Vec::new();
+        let mut body_metrics: Vec<BodyMetrics> = Vec::new();
+        let mut records: Vec<Record> = Vec::new();
+        let mut wellness = WellnessSamples::default();
+        let mut utc_offset: Option<i32> = None;
+        let mut workout: Option<String> = None;
+        let mut profile: Option<String> = None;
+
         while !input.is_empty() {
             let (remaining, object) = processor.deserialize_next(input)?;
             let record = match object {
@@ -142,7 +552,10 @@ impl DatabaseArgs {
                     continue;
                 }
                 FitObject::DataMessage(message)
-                    if Self::should_decode_message(message.global_message_number()) =>
+                    if Self::should_decode_message(
+                        message.global_message_number(),
+                        import_records,
+                    ) =>
                 {
                     processor.decode_message(message)?
                 }
@@ -161,6 +574,15 @@ impl DatabaseArgs {
                     for field in record.fields() {
                         match field.name() {
                             "sport" => curr_session.sport = field.value().to_string(),
+                            "sub_sport" => curr_session.sub_sport = Some(field.value().to_string()),
+                            "sport_profile_name" => {
+                                curr_session.profile = Self::non_empty(field.value().to_string())
+                            }
+                            "pool_length" => {
+                                let pool_length: Option<f64> =
+                                    field.clone().into_value().try_into().ok();
+                                curr_session.pool_length = pool_length.filter(|l| *l > 0.0)
+                            }
                             "total_timer_time" => {
                                 curr_session.duration = field.clone().into_value().try_into()?
                             }
@@ -203,7 +625,7 @@ impl DatabaseArgs {
                             "workout_rpe" => {
                                 let workout_rpe: f64 = field.clone().into_value().try_into()?;
                                 curr_session.rpe = if workout_rpe > 0.0 {
-                                    Some(workout_rpe/10.0)
+                                    Some(workout_rpe / 10.0)
                                 } else {
                                     None
                                 };
@@ -249,6 +671,216 @@ impl DatabaseArgs {
                         }
                     }
                 }
+                MesgNum::Lap => {
+                    let start_time = record
+                        .fields()
+                        .iter()
+                        .find(|field| field.name() == "start_time")
+                        .map(|field| field.clone().into_value());
+                    let Some(Value::Timestamp(local_dt)) = start_time else {
+                        input = remaining;
+                        continue;
+                    };
+
+                    let mut curr_lap = Lap::new(local_dt);
+                    for field in record.fields() {
+                        let value: Option<f64> = field.clone().into_value().try_into().ok();
+                        match field.name() {
+                            "total_timer_time" => curr_lap.duration = value.unwrap_or(0.0),
+                            "total_distance" => curr_lap.distance = value.filter(|v| *v > 0.0),
+                            "avg_heart_rate" => curr_lap.avg_hr = value.filter(|v| *v > 0.0),
+                            "max_heart_rate" => curr_lap.max_hr = value.filter(|v| *v > 0.0),
+                            "avg_power" => curr_lap.avg_power = value.filter(|v| *v > 0.0),
+                            "avg_cadence" | "avg_running_cadence" => {
+                                curr_lap.avg_cadence = value.filter(|v| *v > 0.0)
+                            }
+                            "lap_trigger" => curr_lap.trigger = Some(field.value().to_string()),
+                            _ => {}
+                        }
+                    }
+                    laps.push(curr_lap);
+                }
+                MesgNum::Set => {
+                    // rest periods between sets are recorded as sets too
+                    let is_rest = record.fields().iter().any(|field| {
+                        field.name() == "set_type" && field.value().to_string() == "rest"
+                    });
+                    let start_time = record
+                        .fields()
+                        .iter()
+                        .find(|field| field.name() == "start_time")
+                        .or_else(|| {
+                            record
+                                .fields()
+                                .iter()
+                                .find(|field| field.name() == "timestamp")
+                        })
+                        .map(|field| field.clone().into_value());
+                    let (false, Some(Value::Timestamp(local_dt))) = (is_rest, start_time) else {
+                        input = remaining;
+                        continue;
+                    };
+
+                    let mut curr_set = Set::new(local_dt);
+                    for field in record.fields() {
+                        match field.name() {
+                            "duration" => {
+                                curr_set.duration = field.clone().into_value().try_into().ok()
+                            }
+                            "repetitions" => {
+                                let reps: Option<f64> = field.clone().into_value().try_into().ok();
+                                curr_set.repetitions = reps.map(|reps| reps as i64)
+                            }
+                            "weight" => {
+                                let weight: Option<f64> =
+                                    field.clone().into_value().try_into().ok();
+                                curr_set.weight = weight.filter(|w| *w > 0.0)
+                            }
+                            "category" => {
+                                curr_set.exercise = Self::exercise_category(field.value())
+                            }
+                            _ => {}
+                        }
+                    }
+                    sets.push(curr_set);
+                }
+                MesgNum::Length => {
+                    let start_time = record
+                        .fields()
+                        .iter()
+                        .find(|field| field.name() == "start_time")
+                        .map(|field| field.clone().into_value());
+                    let Some(Value::Timestamp(local_dt)) = start_time else {
+                        input = remaining;
+                        continue;
+                    };
+
+                    let mut curr_length = Length::new(local_dt);
+                    for field in record.fields() {
+                        let value: Option<f64> = field.clone().into_value().try_into().ok();
+                        match field.name() {
+                            "total_timer_time" => curr_length.duration = value,
+                            "total_strokes" => {
+                                curr_length.strokes = value.map(|strokes| strokes as i64)
+                            }
+                            "swim_stroke" => curr_length.stroke = Some(field.value().to_string()),
+                            "length_type" => {
+                                curr_length.active = field.value().to_string() != "idle"
+                            }
+                            _ => {}
+                        }
+                    }
+                    lengths.push(curr_length);
+                }
+                MesgNum::Monitoring
+                | MesgNum::MonitoringHrData
+                | MesgNum::SleepLevel
+                | MesgNum::StressLevel
+                | MesgNum::HsaBodyBatteryData
+                | MesgNum::HrvStatusSummary => wellness.add(record.kind(), record.fields()),
+                MesgNum::WeightScale => {
+                    let timestamp = record
+                        .fields()
+                        .iter()
+                        .find(|field| field.name() == "timestamp")
+                        .map(|field| field.clone().into_value());
+                    let Some(Value::Timestamp(local_dt)) = timestamp else {
+                        input = remaining;
+                        continue;
+                    };
+
+                    let mut curr_metrics = BodyMetrics::new(local_dt);
+                    for field in record.fields() {
+                        let raw: Option<f64> = field.clone().into_value().try_into().ok();
+                        let value = raw.filter(|v| *v > 0.0);
+                        match field.name() {
+                            "weight" => curr_metrics.weight = Self::scale_weight(field.value()),
+                            "percent_fat" => curr_metrics.body_fat = value,
+                            "muscle_mass" => curr_metrics.muscle_mass = value,
+                            "bone_mass" => curr_metrics.bone_mass = value,
+                            "percent_hydration" => curr_metrics.hydration = value,
+                            "bmi" => curr_metrics.bmi = value,
+                            "user_profile_index" => {
+                                curr_metrics.user_index = raw.map(|index| index as i64)
+                            }
+                            _ => {}
+                        }
+                    }
+                    if curr_metrics.weight.is_some() {
+                        body_metrics.push(curr_metrics);
+                    }
+                }
+                MesgNum::Workout => {
+                    if let Some(field) = record.fields().iter().find(|f| f.name() == "wkt_name") {
+                        workout = Self::non_empty(field.value().to_string());
+                    }
+                }
+                MesgNum::Sport => {
+                    if let Some(field) = record.fields().iter().find(|f| f.name() == "name") {
+                        profile = Self::non_empty(field.value().to_string());
+                    }
+                }
+                MesgNum::Activity | MesgNum::MonitoringInfo => {
+                    let timestamp = |name: &str| {
+                        record
+                            .fields()
+                            .iter()
+                            .find(|field| field.name() == name)
+                            .and_then(|field| match field.clone().into_value() {
+                                Value::Timestamp(local_dt) => Some(local_dt),
+                                _ => None,
+                            })
+                    };
+                    if let (Some(timestamp), Some(local_timestamp)) =
+                        (timestamp("timestamp"), timestamp("local_timestamp"))
+                    {
+                        utc_offset = Self::machine_epoch_offset().and_then(|machine_offset| {
+                            Self::fit_utc_offset(
+                                timestamp.timestamp(),
+                                local_timestamp.timestamp(),
+                                machine_offset,
+                            )
+                        });
+                    }
+                }
+                MesgNum::Record => {
+                    let timestamp = record
+                        .fields()
+                        .iter()
+                        .find(|field| field.name() == "timestamp")
+                        .map(|field| field.clone().into_value());
+                    let Some(Value::Timestamp(local_dt)) = timestamp else {
+                        input = remaining;
+                        continue;
+                    };
+
+                    let mut curr_record = Record::new(local_dt);
+                    for field in record.fields() {
+                        let value: Option<f64> = field.clone().into_value().try_into().ok();
+                        match field.name() {
+                            "heart_rate" => curr_record.heart_rate = value,
+                            "power" => curr_record.power = value,
+                            "cadence" => curr_record.cadence = value,
+                            "distance" => curr_record.distance = value,
+                            // enhanced fields have a larger range and win over the plain ones
+                            "speed" => curr_record.speed = curr_record.speed.or(value),
+                            "enhanced_speed" => curr_record.speed = value.or(curr_record.speed),
+                            "altitude" => curr_record.altitude = curr_record.altitude.or(value),
+                            "enhanced_altitude" => {
+                                curr_record.altitude = value.or(curr_record.altitude)
+                            }
+                            // positions are stored in semicircles
+                            "position_lat" => {
+                                curr_record.lat = value.map(|v| v * 180.0 / 2f64.powi(31))
+                            }
+                            "position_long" => {
+                                curr_record.lon = value.map(|v| v * 180.0 / 2f64.powi(31))
+                            }
+                            _ => {}
+                        }
+                    }
+                    records.push(curr_record);
+                }
                 _ => {}
             }
 
@@ -261,41 +893,236 @@ impl DatabaseArgs {
             devices.push(curr_device);
         }
 
-        Ok((sessions, devices))
+        Ok(FitData {
+            sessions,
+            devices,
+            laps,
+            sets,
+            lengths,
+            body_metrics,
+            records,
+            wellness,
+            utc_offset,
+            workout,
+            profile,
+        })
+    }
+
+    // category is an array, the first known entry names the exercise
+    fn exercise_category(value: &Value) -> Option<String> {
+        let values = match value {
+            Value::Array(values) => values.as_slice(),
+            value => std::slice::from_ref(value),
+        };
+        values
+            .iter()
+            .map(|value| value.to_string())
+            .find(|name| !name.is_empty() && name != "unknown")
+    }
+
+    // fitparser returns the weight type unscaled, as it also has a "calculating" variant
+    fn scale_weight(value: &Value) -> Option<f64> {
+        let weight: f64 = match value {
+            Value::Float32(_) | Value::Float64(_) => value.clone().try_into().ok()?,
+            Value::String(_) => return None,
+            _ => {
+                let raw: f64 = value.clone().try_into().ok()?;
+                raw / 100.0
+            }
+        };
+        (weight > 0.0).then_some(weight)
+    }
+
+    fn non_empty(value: String) -> Option<String> {
+        let value = value.trim();
+        (!value.is_empty()).then(|| value.to_string())
+    }
+
+    // seconds the FIT epoch in this machine's timezone lies after the FIT epoch in UTC, the
+    // negated UTC offset of this machine at 1989-12-31
+    fn machine_epoch_offset() -> Option<i64> {
+        let fit_epoch = NaiveDate::from_ymd_opt(1989, 12, 31)?.and_hms_opt(0, 0, 0)?;
+        Some(
+            Local.from_local_datetime(&fit_epoch).single()?.timestamp()
+                - fit_epoch.and_utc().timestamp(),
+        )
+    }
+
+    // offset between an activity's UTC and local timestamp in epoch seconds, rounded to 15
+    // minutes; fitparser counts local timestamps from the FIT epoch in this machine's timezone,
+    // which is undone with `machine_epoch_offset`
+    fn fit_utc_offset(
+        timestamp: i64,
+        local_timestamp: i64,
+        machine_epoch_offset: i64,
+    ) -> Option<i32> {
+        let offset = local_timestamp - timestamp - machine_epoch_offset;
+        let offset = (offset as f64 / 900.0).round() as i32 * 900;
+        // offsets range from UTC-12 to UTC+14
+        (-12 * 3600..=14 * 3600).contains(&offset).then_some(offset)
+    }
+
+    // index of the last session that started at or before `timestamp`
+    fn session_index(sessions: &[Activity], timestamp: DateTime<Local>) -> usize {
+        sessions
+            .iter()
+            .rposition(|session| session.timestamp <= timestamp)
+            .unwrap_or(0)
     }
 
     fn add_activity(
-        activity_data: (Vec<Activity>, Vec<Device>),
+        activity_data: FitData,
+        file_id: i64,
+        config: &Config,
         conn: &Connection,
     ) -> anyhow::Result<()> {
-        let (sessions, devices) = activity_data;
+        let FitData {
+            sessions,
+            devices,
+            laps,
+            sets,
+            lengths,
+            body_metrics,
+            records,
+            wellness,
+            utc_offset,
+            workout,
+            profile,
+        } = activity_data;
+
+        // laps, sets, lengths and records belong to the last session that started before them
+        let mut session_laps: Vec<Vec<&Lap>> = vec![Vec::new(); sessions.len()];
+        let mut session_sets: Vec<Vec<&Set>> = vec![Vec::new(); sessions.len()];
+        let mut session_lengths: Vec<Vec<&Length>> = vec![Vec::new(); sessions.len()];
+        let mut session_records: Vec<Vec<&Record>> = vec![Vec::new(); sessions.len()];
+        if !sessions.is_empty() {
+            for lap in &laps {
+                session_laps[Self::session_index(&sessions, lap.start_time)].push(lap);
+            }
+            for set in &sets {
+                session_sets[Self::session_index(&sessions, set.start_time)].push(set);
+            }
+            for length in &lengths {
+                session_lengths[Self::session_index(&sessions, length.start_time)].push(length);
+            }
+            for record in &records {
+                session_records[Self::session_index(&sessions, record.timestamp)].push(record);
+            }
+        }
+
+        let mut activity_ids = Vec::with_capacity(sessions.len());
+        for ((((session, laps), sets), lengths), records) in sessions
+            .iter()
+            .zip(&session_laps)
+            .zip(&session_sets)
+            .zip(&session_lengths)
+            .zip(&session_records)
+        {
+            let utc_offset =
+                utc_offset.unwrap_or_else(|| timestamps::machine_offset(&session.timestamp));
+            conn.execute(
+                "INSERT INTO activities (sport, sub_sport, profile, workout, timestamp, utc_offset, duration, distance, calories, avg_hr, elevation, avg_power, rpe, rpe_est, pool_length, file_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+                params![session.sport, session.sub_sport, session.profile.as_ref().or(profile.as_ref()), workout, session.timestamp.timestamp(), utc_offset, session.duration, session.distance, session.calories, session.avg_hr, session.elevation, session.avg_power, session.rpe, session.rpe_est, session.pool_length, file_id],
+            )?;
+            let activity_id = conn.last_insert_rowid();
+            activity_ids.push(activity_id);
+
+            for (index, lap) in laps.iter().enumerate() {
+                conn.execute(
+                    "INSERT INTO laps (activity_id, lap, start_time, duration, distance, avg_hr, max_hr, avg_power, avg_cadence, lap_trigger) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    params![activity_id, index as i64 + 1, lap.start_time.timestamp(), lap.duration, lap.distance, lap.avg_hr, lap.max_hr, lap.avg_power, lap.avg_cadence, lap.trigger],
+                )?;
+            }
+
+            for (index, set) in sets.iter().enumerate() {
+                conn.execute(
+                    "INSERT INTO sets (activity_id, set_index, start_time, duration, repetitions, weight, exercise) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![activity_id, index as i64 + 1, set.start_time.timestamp(), set.duration, set.repetitions, set.weight, set.exercise],
+                )?;
+            }
+
+            for (index, length) in lengths.iter().enumerate() {
+                conn.execute(
+                    "INSERT INTO lengths (activity_id, length, start_time, duration, strokes, stroke, active) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![activity_id, index as i64 + 1, length.start_time.timestamp(), length.duration, length.strokes, length.stroke, length.active],
+                )?;
+            }
+
+            if let Some(stream) = Stream::from_records(session.timestamp, records) {
+                stream.save(conn, activity_id)?;
 
-        for session in sessions {
+                // zones in effect on the activity's local date
+                let date = FixedOffset::east_opt(utc_offset)
+                    .map_or(session.timestamp.date_naive(), |offset| {
+                        session.timestamp.with_timezone(&offset).date_naive()
+                    });
+                let athlete = body::athlete_on(config, conn, date)?;
+                let zones = TimeInZones::compute(&stream, &session.sport, &athlete);
+                TimeInZones::save_all(conn, activity_id, &zones)?;
+
+                let efforts = BestEffort::compute(&stream, &session.sport);
+                BestEffort::save_all(conn, activity_id, &efforts)?;
+            }
+        }
+
+        for metrics in body_metrics {
             conn.execute(
-                "INSERT INTO activities (sport, timestamp, duration, distance, calories, avg_hr, elevation, avg_power, rpe, rpe_est) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-                params![session.sport, session.timestamp.to_rfc3339(), session.duration, session.distance, session.calories, session.avg_hr, session.elevation, session.avg_power, session.rpe, session.rpe_est],
+                "INSERT INTO body_metrics (file_id, timestamp, user_index, weight, body_fat, muscle_mass, bone_mass, hydration, bmi) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![file_id, metrics.timestamp.timestamp(), metrics.user_index, metrics.weight, metrics.body_fat, metrics.muscle_mass, metrics.bone_mass, metrics.hydration, metrics.bmi],
             )?;
         }
 
+        if !wellness.is_empty() {
+            WellnessDay::save_all(conn, file_id, &wellness.days(utc_offset))?;
+        }
+
         for device in devices {
             conn.execute(
-                "INSERT INTO devices (product, timestamp, battery, battery_status) VALUES (?1, ?2, ?3, ?4)",
+                "INSERT INTO devices (product, timestamp, battery, battery_status, file_id) VALUES (?1, ?2, ?3, ?4, ?5)",
                 params![
                     device.product,
-                    device.timestamp.to_rfc3339(),
+                    device.timestamp.timestamp(),
                     device.battery,
                     device.battery_status,
+                    file_id,
                 ],
             )?;
+            let device_id = conn.last_insert_rowid();
+
+            // a file does not say which of its sessions a device took part in
+            for activity_id in &activity_ids {
+                conn.execute(
+                    "INSERT INTO activity_devices (activity_id, device_id) VALUES (?1, ?2)",
+                    params![activity_id, device_id],
+                )?;
+            }
         }
         Ok(())
     }
 
-    fn should_decode_message(global_message_number: u16) -> bool {
-        matches!(
-            MesgNum::from(global_message_number),
-            MesgNum::Session | MesgNum::DeviceInfo | MesgNum::FieldDescription
-        )
+    fn should_decode_message(global_message_number: u16, import_records: bool) -> bool {
+        match MesgNum::from(global_message_number) {
+            MesgNum::Session
+            | MesgNum::Lap
+            | MesgNum::Set
+            | MesgNum::Length
+            | MesgNum::Monitoring
+            | MesgNum::MonitoringInfo
+            | MesgNum::MonitoringHrData
+            | MesgNum::SleepLevel
+            | MesgNum::StressLevel
+            | MesgNum::HsaBodyBatteryData
+            | MesgNum::HrvStatusSummary
+            | MesgNum::WeightScale
+            | MesgNum::DeviceInfo
+            | MesgNum::Activity
+            | MesgNum::Workout
+            | MesgNum::Sport
+            | MesgNum::FieldDescription => true,
+            // record messages make up most of a file, only decode them when they are imported
+            MesgNum::Record => import_records,
+            _ => false,
+        }
     }
 
     fn get_filename(path: &Path) -> anyhow::Result<String> {
@@ -306,22 +1133,366 @@ impl DatabaseArgs {
         Ok(filename.to_owned())
     }
 
-    fn check_file_imported(file: &File, conn: &Connection) -> anyhow::Result<bool> {
+    fn hash_file(path: &Path) -> anyhow::Result<String> {
+        let digest = Sha256::digest(std::fs::read(path)?);
+        Ok(digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+    }
+
+    fn get_stored_files(conn: &Connection) -> anyhow::Result<Vec<StoredFile>> {
+        let mut stmt = conn.prepare("SELECT id, path, hash, size, mtime, duplicate FROM files")?;
+        let files = stmt
+            .query_map(params![], |row| {
+                Ok(StoredFile {
+                    id: row.get(0)?,
+                    path: row.get(1)?,
+                    hash: row.get(2)?,
+                    size: row.get(3)?,
+                    mtime: row.get(4)?,
+                    duplicate: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<StoredFile>, _>>()?;
+
+        Ok(files)
+    }
+
+    // true if the file failed to import and has not changed since
+    fn check_file_failed(
+        path: &str,
+        size: i64,
+        mtime: i64,
+        conn: &Connection,
+    ) -> anyhow::Result<bool> {
         let exists: bool = conn.query_row(
-            "SELECT EXISTS(SELECT 1 FROM files WHERE filename = ?1)",
-            params![file.filename],
+            "SELECT EXISTS(SELECT 1 FROM import_errors WHERE path = ?1 AND size = ?2 AND mtime = ?3)",
+            params![path, size, mtime],
             |row| row.get(0),
         )?;
 
         Ok(exists)
     }
 
-    fn add_filename(file: &File, conn: &Connection) -> anyhow::Result<()> {
+    fn add_import_error(
+        file: &File,
+        error: &anyhow::Error,
+        conn: &Connection,
+    ) -> anyhow::Result<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO import_errors (path, filename, size, mtime, error, app_version, timestamp) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                file.path,
+                file.filename,
+                file.size,
+                file.mtime,
+                format!("{:#}", error),
+                VERSION,
+                Local::now().timestamp()
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn clear_import_error(file: &File, conn: &Connection) -> anyhow::Result<()> {
         conn.execute(
-            "INSERT INTO files (filename) VALUES (?1)",
-            params![file.filename],
+            "DELETE FROM import_errors WHERE path = ?1",
+            params![file.path],
         )?;
 
         Ok(())
     }
+
+    fn add_file(file: &File, conn: &Connection) -> anyhow::Result<i64> {
+        conn.execute(
+            "INSERT INTO files (filename, path, hash, size, mtime) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![file.filename, file.path, file.hash, file.size, file.mtime],
+        )?;
+
+        Ok(conn.last_insert_rowid())
+    }
+
+    // record a file with the same contents as an imported one, so it is skipped by size and
+    // modification time on later imports
+    fn add_duplicate(file: &File, conn: &Connection) -> anyhow::Result<()> {
+        conn.execute(
+            "INSERT INTO files (filename, path, hash, size, mtime, duplicate) VALUES (?1, ?2, ?3, ?4, ?5, 1)",
+            params![file.filename, file.path, file.hash, file.size, file.mtime],
+        )?;
+
+        Ok(())
+    }
+
+    // point a file row at the file's current path and metadata
+    fn update_file(id: i64, file: &File, conn: &Connection) -> anyhow::Result<()> {
+        conn.execute(
+            "UPDATE files SET filename = ?1, path = ?2, hash = ?3, size = ?4, mtime = ?5 WHERE id = ?6",
+            params![file.filename, file.path, file.hash, file.size, file.mtime, id],
+        )?;
+
+        Ok(())
+    }
+
+    // removes the activities and devices imported from the file as well
+    fn remove_file(id: i64, conn: &Connection) -> anyhow::Result<()> {
+        conn.execute("DELETE FROM files WHERE id = ?1", params![id])?;
+
+        Ok(())
+    }
+}
+
+impl WriteProgress {
+    fn new(window: usize) -> Self {
+        Self {
+            written: Mutex::new(0),
+            advanced: Condvar::new(),
+            window,
+        }
+    }
+
+    // blocks until file `index` is within the window of the writer
+    fn wait_for(&self, index: usize) {
+        let written = self.written.lock().unwrap_or_else(PoisonError::into_inner);
+        let _written = self
+            .advanced
+            .wait_while(written, |written| {
+                index >= written.saturating_add(self.window)
+            })
+            .unwrap_or_else(PoisonError::into_inner);
+    }
+
+    fn advance(&self, written: usize) {
+        *self.written.lock().unwrap_or_else(PoisonError::into_inner) = written;
+        self.advanced.notify_all();
+    }
+}
+
+impl Drop for ReleaseDecoders<'_> {
+    fn drop(&mut self) {
+        self.0.advance(usize::MAX);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_utc_offset_does_not_depend_on_machine_timezone() {
+        // 2024-06-01 08:00 UTC recorded at UTC+2
+        let timestamp = 1_717_228_800;
+        let local_seconds = timestamp - 631_065_600 + 7200;
+        // machines at UTC, UTC+1, UTC-5 and UTC+9
+        for machine_utc_offset in [0, 3600, -5 * 3600, 9 * 3600] {
+            // fitparser's local timestamp: the FIT epoch in the machine's timezone plus the
+            // local seconds of the file
+            let machine_epoch_offset = -machine_utc_offset;
+            let local_timestamp = 631_065_600 + machine_epoch_offset + local_seconds;
+            assert_eq!(
+                DatabaseArgs::fit_utc_offset(timestamp, local_timestamp, machine_epoch_offset),
+                Some(7200),
+                "machine offset {}",
+                machine_utc_offset
+            );
+        }
+    }
+
+    #[test]
+    fn fit_utc_offset_rounds_and_rejects_impossible_offsets() {
+        assert_eq!(
+            DatabaseArgs::fit_utc_offset(0, 5 * 3600 + 30, 0),
+            Some(5 * 3600)
+        );
+        assert_eq!(
+            DatabaseArgs::fit_utc_offset(0, 5 * 3600 + 1800, 0),
+            Some(5 * 3600 + 1800)
+        );
+        assert_eq!(DatabaseArgs::fit_utc_offset(0, 15 * 3600, 0), None);
+        assert_eq!(DatabaseArgs::fit_utc_offset(0, -13 * 3600, 0), None);
+    }
+
+    #[test]
+    fn decoders_wait_for_the_writer_to_catch_up() {
+        let progress = WriteProgress::new(2);
+        // within the window, nothing to wait for
+        progress.wait_for(1);
+
+        thread::scope(|scope| {
+            let waiting = scope.spawn(|| progress.wait_for(3));
+            thread::sleep(Duration::from_millis(20));
+            assert!(!waiting.is_finished());
+            progress.advance(2);
+            waiting.join().unwrap();
+
+            let waiting = scope.spawn(|| progress.wait_for(100));
+            drop(ReleaseDecoders(&progress));
+            waiting.join().unwrap();
+        });
+    }
+
+    // a data directory with the files table it was imported into
+    struct Library {
+        dir: std::path::PathBuf,
+        conn: Connection,
+    }
+
+    impl Library {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("queryfit-{}-{}", process::id(), name));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            let conn = Connection::open_in_memory().unwrap();
+            conn.execute_batch(
+                "CREATE TABLE files (id INTEGER PRIMARY KEY, filename TEXT NOT NULL, path TEXT NOT NULL UNIQUE, hash TEXT NOT NULL, size INTEGER NOT NULL, mtime INTEGER NOT NULL, duplicate INTEGER NOT NULL DEFAULT 0);
+                CREATE TABLE import_errors (path TEXT PRIMARY KEY, filename TEXT NOT NULL, size INTEGER NOT NULL, mtime INTEGER NOT NULL, error TEXT NOT NULL, app_version TEXT NOT NULL, timestamp DATETIME NOT NULL);",
+            )
+            .unwrap();
+            Self { dir, conn }
+        }
+
+        fn write(&self, name: &str, contents: &str) {
+            std::fs::write(self.dir.join(name), contents).unwrap();
+        }
+
+        // plans an import and adds the pending files as the writer would
+        fn import(&self) -> ImportPlan {
+            let files: Vec<_> = walkdir::WalkDir::new(&self.dir)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.path().extension().is_some_and(|ext| ext == "fit"))
+                .collect();
+            let plan = DatabaseArgs::plan_import(&files, &self.conn, false, &ProgressBar::hidden())
+                .unwrap();
+            for pending in &plan.pending {
+                if let Some(id) = pending.replaces {
+                    DatabaseArgs::remove_file(id, &self.conn).unwrap();
+                }
+                DatabaseArgs::add_file(&pending.file, &self.conn).unwrap();
+            }
+            plan
+        }
+
+        fn pending(plan: &ImportPlan) -> Vec<(&str, Option<i64>)> {
+            plan.pending
+                .iter()
+                .map(|pending| (pending.file.filename.as_str(), pending.replaces))
+                .collect()
+        }
+
+        // filename, id and duplicate flag of every file row
+        fn files(&self) -> Vec<(String, i64, bool)> {
+            let mut files: Vec<_> = DatabaseArgs::get_stored_files(&self.conn)
+                .unwrap()
+                .into_iter()
+                .map(|file| {
+                    let filename = Path::new(&file.path).file_name().unwrap();
+                    (
+                        filename.to_string_lossy().into_owned(),
+                        file.id,
+                        file.duplicate,
+                    )
+                })
+                .collect();
+            files.sort();
+            files
+        }
+    }
+
+    impl Drop for Library {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn plan_import_skips_duplicates_and_follows_renames() {
+        let library = Library::new("renames");
+        library.write("a.fit", "ride");
+        library.write("b.fit", "ride");
+        library.write("c.fit", "run");
+
+        // duplicates are recorded while planning, before the pending files are written
+        let plan = library.import();
+        assert_eq!(
+            Library::pending(&plan),
+            vec![("a.fit", None), ("c.fit", None)]
+        );
+        assert_eq!(plan.duplicates, 1);
+        assert_eq!(
+            library.files(),
+            vec![
+                ("a.fit".to_string(), 2, false),
+                ("b.fit".to_string(), 1, true),
+                ("c.fit".to_string(), 3, false),
+            ]
+        );
+
+        // the skipped duplicate is known now: with the same size and mtime it is neither hashed
+        // nor reported again, even though its contents changed behind the mtime's back
+        let duplicate = library.dir.join("b.fit");
+        let modified = std::fs::metadata(&duplicate).unwrap().modified().unwrap();
+        library.write("b.fit", "swim");
+        std::fs::File::options()
+            .write(true)
+            .open(&duplicate)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        let plan = library.import();
+        assert!(plan.pending.is_empty());
+        assert_eq!((plan.duplicates, plan.renamed, plan.removed), (0, 0, 0));
+
+        // a renamed file keeps its row
+        std::fs::rename(library.dir.join("c.fit"), library.dir.join("d.fit")).unwrap();
+        let plan = library.import();
+        assert!(plan.pending.is_empty());
+        assert_eq!((plan.duplicates, plan.renamed, plan.removed), (0, 1, 0));
+        assert!(library.files().contains(&("d.fit".to_string(), 3, false)));
+    }
+
+    #[test]
+    fn plan_import_replaces_changed_and_removes_deleted_files() {
+        let library = Library::new("changes");
+        library.write("a.fit", "ride");
+        library.write("b.fit", "ride");
+        library.write("c.fit", "run");
+        library.import();
+
+        // the changed file replaces its rows, and its duplicate has the old contents imported
+        library.write("a.fit", "longer ride");
+        let plan = library.import();
+        assert_eq!(
+            Library::pending(&plan),
+            vec![("a.fit", Some(2)), ("b.fit", None)]
+        );
+        assert_eq!(
+            library.files(),
+            vec![
+                ("a.fit".to_string(), 4, false),
+                ("b.fit".to_string(), 5, false),
+                ("c.fit".to_string(), 3, false),
+            ]
+        );
+
+        std::fs::remove_file(library.dir.join("c.fit")).unwrap();
+        let plan = library.import();
+        assert!(plan.pending.is_empty());
+        assert_eq!((plan.duplicates, plan.renamed, plan.removed), (0, 0, 1));
+        assert_eq!(library.files().len(), 2);
+    }
+
+    #[test]
+    fn plan_import_moves_a_deleted_file_to_its_duplicate() {
+        let library = Library::new("deleted");
+        library.write("a.fit", "ride");
+        library.write("b.fit", "ride");
+        library.import();
+
+        // the duplicate takes the place of the deleted file without importing it again
+        std::fs::remove_file(library.dir.join("a.fit")).unwrap();
+        let plan = library.import();
+        assert!(plan.pending.is_empty());
+        assert_eq!((plan.duplicates, plan.renamed, plan.removed), (0, 1, 0));
+        assert_eq!(library.files(), vec![("b.fit".to_string(), 2, false)]);
+    }
 }
@@ -1,9 +1,11 @@
+use crate::config::Config;
 use crate::db::Database;
-use crate::{config::Config, models::Device};
+use crate::output::OutputFormat;
+use crate::timestamps;
 use chrono::{DateTime, Local};
 use clap::{Args, Subcommand};
-use rusqlite::types::Type;
 use rusqlite::params;
+use serde::Serialize;
 
 #[derive(Debug, Args)]
 pub struct DevicesArgs {
@@ -18,35 +20,57 @@ pub enum Actions {
     List,
 }
 
+#[derive(Debug, Serialize)]
+pub struct DeviceEntry {
+    pub product: String,
+    // last seen
+    pub timestamp: DateTime<Local>,
+    // in V
+    pub battery: Option<f64>,
+    pub battery_status: Option<String>,
+    // number of activities the device was used for
+    pub activities: i64,
+}
+
 impl DevicesArgs {
-    pub fn run(&self, config: &Config, db: &Database) -> anyhow::Result<()> {
+    pub fn run(&self, config: &Config, db: &Database, format: OutputFormat) -> anyhow::Result<()> {
         match &self.actions {
-            Actions::List => self.run_list(config, db),
+            Actions::List => self.run_list(config, db, format),
         }
     }
 
-    fn run_list(&self, _config: &Config, db: &Database) -> anyhow::Result<()> {
+    fn run_list(
+        &self,
+        _config: &Config,
+        db: &Database,
+        format: OutputFormat,
+    ) -> anyhow::Result<()> {
         let devices = Self::get_all_devices(db)?;
 
+        format.print_rows(&devices, || Self::print_devices(&devices))
+    }
+
+    fn print_devices(devices: &[DeviceEntry]) -> anyhow::Result<()> {
         if devices.is_empty() {
             println!("No devices found.");
             return Ok(());
         }
 
         println!(
-            "{:<20} {:<12} {:<10} {:<10} {}",
-            "Product", "Last Seen", "Battery", "Status", "Timestamp"
+            "{:<20} {:<12} {:<10} {:<10} {:>10}  Timestamp",
+            "Product", "Last Seen", "Battery", "Status", "Activities"
         );
 
         for device in devices {
             let battery = Self::format_battery(device.battery);
             let status = Self::format_battery_status(device.battery_status.as_deref());
             println!(
-                "{:<20} {:<12} {:<10} {:<10} {}",
+                "{:<20} {:<12} {:<10} {:<10} {:>10}  {}",
                 device.product,
                 Self::format_last_seen(device.timestamp),
                 battery,
                 status,
+                device.activities,
                 device.timestamp.format("%Y-%m-%d %H:%M"),
             );
         }
@@ -54,7 +78,7 @@ impl DevicesArgs {
         Ok(())
     }
 
-    fn get_all_devices(db: &Database) -> anyhow::Result<Vec<Device>> {
+    fn get_all_devices(db: &Database) -> anyhow::Result<Vec<DeviceEntry>> {
         let query = "
             WITH latest_seen AS (
                 SELECT product, MAX(timestamp) AS timestamp
@@ -80,7 +104,13 @@ impl DevicesArgs {
                       AND devices.battery_status != ''
                     ORDER BY devices.timestamp DESC, devices.rowid DESC
                     LIMIT 1
-                ) AS battery_status
+                ) AS battery_status,
+                (
+                    SELECT COUNT(DISTINCT activity_devices.activity_id)
+                    FROM devices
+                    JOIN activity_devices ON activity_devices.device_id = devices.id
+                    WHERE devices.product = latest_seen.product
+                ) AS activities
             FROM latest_seen
             ORDER BY latest_seen.timestamp DESC, latest_seen.product ASC
         ";
@@ -88,20 +118,16 @@ impl DevicesArgs {
         let mut stmt = db.connection().prepare(query)?;
 
         let devices = stmt.query_map(params![], |row| {
-            let datetime_string: String = row.get(1)?;
-            let parsed_datetime = DateTime::parse_from_rfc3339(&datetime_string).map_err(|err| {
-                rusqlite::Error::FromSqlConversionFailure(1, Type::Text, Box::new(err))
-            })?;
-
-            Ok(Device {
+            Ok(DeviceEntry {
                 product: row.get(0)?,
-                timestamp: parsed_datetime.into(),
+                timestamp: timestamps::local_time(row, 1)?,
                 battery: row.get(2)?,
                 battery_status: row.get(3)?,
+                activities: row.get(4)?,
             })
         })?;
 
-        let result: Vec<Device> = devices.collect::<Result<Vec<Device>, _>>()?;
+        let result: Vec<DeviceEntry> = devices.collect::<Result<Vec<DeviceEntry>, _>>()?;
 
         Ok(result)
     }
@@ -114,7 +140,9 @@ impl DevicesArgs {
     }
 
     fn format_battery_status(status: Option<&str>) -> &str {
-        status.filter(|value| !value.is_empty()).unwrap_or("unknown")
+        status
+            .filter(|value| !value.is_empty())
+            .unwrap_or("unknown")
     }
 
     fn format_last_seen(timestamp: DateTime<Local>) -> String {
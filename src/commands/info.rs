@@ -1,45 +1,81 @@
 use crate::VERSION;
 use crate::config::Config;
 use crate::db::Database;
+use crate::output::OutputFormat;
 use anyhow::Context;
 use clap::Args;
 use rusqlite::params;
+use serde::Serialize;
+use std::path::PathBuf;
 use walkdir::WalkDir;
 
 #[derive(Debug, Args)]
 pub struct InfoArgs {}
 
+#[derive(Debug, Serialize)]
+pub struct Info {
+    pub app_version: String,
+    pub database_version: String,
+    pub schema_version: Option<i64>,
+    pub database_valid: bool,
+    pub data_location: PathBuf,
+    pub imported_files: i64,
+    pub failed_files: i64,
+    pub total_files: i64,
+    // in bytes
+    pub total_files_size: u64,
+    // in bytes
+    pub database_size: u64,
+}
+
 impl InfoArgs {
-    pub fn run(&self, config: &Config, db: &Database) -> anyhow::Result<()> {
-        println!("{:<25}: {}", "App version", VERSION);
-        println!("{:<25}: {:?}", "Database version", Self::get_version(db)?);
-        if !db.get_db_validitiy() {
-            println!("{:<25}: {}", "Database Status", "INVALID");
+    pub fn run(&self, config: &Config, db: &Database, format: OutputFormat) -> anyhow::Result<()> {
+        let info = Info {
+            app_version: VERSION.to_string(),
+            database_version: Self::get_version(db)?,
+            schema_version: db.schema_version()?,
+            database_valid: db.get_db_validitiy(),
+            data_location: config.get_data_path().to_owned(),
+            imported_files: Self::get_num_files_in_db(db)?,
+            failed_files: Self::get_num_failed_files(db)?,
+            total_files: Self::get_num_fit_files(config)?,
+            total_files_size: Self::get_all_data_size(config)?,
+            database_size: Self::get_db_size(db)?,
+        };
+
+        format.print_document(&info, std::slice::from_ref(&info), || {
+            Self::print_info(&info);
+            Ok(())
+        })
+    }
+
+    fn print_info(info: &Info) {
+        println!("{:<25}: {}", "App version", info.app_version);
+        println!("{:<25}: {:?}", "Database version", info.database_version);
+        match info.schema_version {
+            Some(version) => println!("{:<25}: {}", "Schema version", version),
+            None => println!("{:<25}: unknown", "Schema version"),
+        }
+        if !info.database_valid {
+            println!("{:<25}: REIMPORT REQUIRED", "Database Status");
             println!("Please run 'queryfit database recreate'. No data will be lost.");
         }
-        println!("{:<25}: {:?}", "Data location", config.get_data_path());
-        println!(
-            "{:<25}: {}",
-            "Imported .fit files",
-            Self::get_num_files_in_db(db)?
-        );
-        println!(
-            "{:<25}: {}",
-            "Total .fit files",
-            Self::get_num_fit_files(config)?
-        );
+        println!("{:<25}: {:?}", "Data location", info.data_location);
+        println!("{:<25}: {}", "Imported .fit files", info.imported_files);
+        if info.failed_files > 0 {
+            println!("{:<25}: {}", "Failed .fit files", info.failed_files);
+        }
+        println!("{:<25}: {}", "Total .fit files", info.total_files);
         println!(
             "{:<25}: {}",
             "Total .fit files size",
-            Self::format_file_size(Self::get_all_data_size(config)?)
+            Self::format_file_size(info.total_files_size)
         );
         println!(
             "{:<25}: {}",
             "Database size",
-            Self::format_file_size(Self::get_db_size(db)?)
+            Self::format_file_size(info.database_size)
         );
-
-        Ok(())
     }
 
     fn get_version(db: &Database) -> anyhow::Result<String> {
@@ -60,6 +96,15 @@ impl InfoArgs {
         Ok(count)
     }
 
+    fn get_num_failed_files(db: &Database) -> anyhow::Result<i64> {
+        let count: i64 =
+            db.connection()
+                .query_row("SELECT COUNT(*) FROM import_errors", params![], |row| {
+                    row.get(0)
+                })?;
+        Ok(count)
+    }
+
     fn get_num_fit_files(config: &Config) -> anyhow::Result<i64> {
         let count: usize = WalkDir::new(config.get_data_path())
             .into_iter()
@@ -68,7 +113,7 @@ impl InfoArgs {
                 entry
                     .path()
                     .extension()
-                    .map_or(false, |ext| ext.eq_ignore_ascii_case("fit"))
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("fit"))
             })
             .count();
 
@@ -84,7 +129,7 @@ impl InfoArgs {
                 if path.is_file()
                     && path
                         .extension()
-                        .map_or(false, |ext| ext.eq_ignore_ascii_case("fit"))
+                        .is_some_and(|ext| ext.eq_ignore_ascii_case("fit"))
                 {
                     path.metadata().ok().map(|metadata| metadata.len())
                 } else {
@@ -1,13 +1,23 @@
+pub mod activity;
+pub mod body;
 pub mod calculate;
 pub mod config;
 pub mod database;
 pub mod devices;
 pub mod info;
+pub mod query;
+pub mod strength;
 pub mod summary;
+pub mod wellness;
 
+pub use activity::ActivityArgs;
+pub use body::BodyArgs;
 pub use calculate::CalculateArgs;
 pub use config::ConfigArgs;
 pub use database::DatabaseArgs;
 pub use devices::DevicesArgs;
 pub use info::InfoArgs;
+pub use query::QueryArgs;
+pub use strength::StrengthArgs;
 pub use summary::SummaryArgs;
+pub use wellness::WellnessArgs;
@@ -0,0 +1,205 @@
+use crate::config::Config;
+use crate::db::Database;
+use crate::filter::{Condition, Field, FieldKind};
+use crate::output::OutputFormat;
+use crate::timestamps;
+use chrono::{DateTime, FixedOffset};
+use clap::Args;
+use rusqlite::params_from_iter;
+use rusqlite::types::Value;
+use serde::Serialize;
+use serde::ser::{SerializeStruct, Serializer};
+
+#[derive(Debug, Args)]
+pub struct QueryArgs {
+    #[arg(help = "filter, e.g. \"sport = running and distance > 10km and date in 2026-Q2\"")]
+    pub filter: Option<String>,
+
+    #[arg(long, value_delimiter = ',', value_parser = Field::parse)]
+    #[arg(default_value = "id,date,sport,duration,distance,avg_hr")]
+    #[arg(help = "comma separated fields to show")]
+    pub columns: Vec<Field>,
+
+    #[arg(long, value_parser = Field::parse, default_value = "date")]
+    #[arg(help = "field to sort by, newest or largest first")]
+    pub sort: Field,
+
+    #[arg(long, help = "sort ascending")]
+    pub asc: bool,
+
+    #[arg(long, help = "maximum number of activities")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+enum Cell {
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Time(DateTime<FixedOffset>),
+    Null,
+}
+
+// one activity with the requested fields in order
+#[derive(Debug)]
+struct QueryRow(Vec<(&'static str, Cell)>);
+
+impl Serialize for QueryRow {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut row = serializer.serialize_struct("QueryRow", self.0.len())?;
+        for (name, cell) in &self.0 {
+            row.serialize_field(name, cell)?;
+        }
+        row.end()
+    }
+}
+
+impl QueryArgs {
+    pub fn run(&self, _config: &Config, db: &Database, format: OutputFormat) -> anyhow::Result<()> {
+        let condition = match &self.filter {
+            Some(filter) => Condition::parse(filter)?,
+            None => Condition::all(),
+        };
+        let rows = self.fetch_rows(db, condition)?;
+
+        format.print_rows(&rows, || {
+            self.print_rows(&rows);
+            Ok(())
+        })
+    }
+
+    fn fetch_rows(&self, db: &Database, condition: Condition) -> anyhow::Result<Vec<QueryRow>> {
+        // the date is read from the timestamp and the offset it was recorded with
+        let columns: Vec<&str> = self
+            .columns
+            .iter()
+            .map(|field| match field {
+                Field::Date => "activities.timestamp, activities.utc_offset",
+                _ => field.column(),
+            })
+            .collect();
+        let mut query = format!(
+            "SELECT {} FROM activities WHERE {} ORDER BY {} {}",
+            columns.join(", "),
+            condition.sql,
+            self.sort.column(),
+            if self.asc { "ASC" } else { "DESC" }
+        );
+        let mut values = condition.values;
+        if let Some(limit) = self.limit {
+            query.push_str(" LIMIT ?");
+            values.push(Value::Integer(limit.into()));
+        }
+
+        let mut stmt = db.connection().prepare(&query)?;
+        let rows = stmt
+            .query_map(params_from_iter(&values), |row| {
+                let mut cells = Vec::with_capacity(self.columns.len());
+                let mut index = 0;
+                for field in &self.columns {
+                    let cell = match field.kind() {
+                        FieldKind::Date => {
+                            let time = timestamps::activity_time(row, index, index + 1)?;
+                            index += 1;
+                            Cell::Time(time)
+                        }
+                        FieldKind::Integer => row
+                            .get::<_, Option<i64>>(index)?
+                            .map_or(Cell::Null, Cell::Integer),
+                        FieldKind::Text => row
+                            .get::<_, Option<String>>(index)?
+                            .map_or(Cell::Null, Cell::Text),
+                        _ => row
+                            .get::<_, Option<f64>>(index)?
+                            .map_or(Cell::Null, Cell::Real),
+                    };
+                    cells.push((field.name(), cell));
+                    index += 1;
+                }
+                Ok(QueryRow(cells))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(rows)
+    }
+
+    fn print_rows(&self, rows: &[QueryRow]) {
+        if rows.is_empty() {
+            println!("No matching activities.");
+            return;
+        }
+
+        let table: Vec<Vec<String>> = rows
+            .iter()
+            .map(|row| {
+                self.columns
+                    .iter()
+                    .zip(&row.0)
+                    .map(|(field, (_, cell))| Self::format_cell(*field, cell))
+                    .collect()
+            })
+            .collect();
+        let widths: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                table
+                    .iter()
+                    .map(|cells| cells[i].len())
+                    .chain([field.name().len()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let header: Vec<String> = self
+            .columns
+            .iter()
+            .zip(&widths)
+            .map(|(field, width)| format!("{:<width$}", field.name(), width = width))
+            .collect();
+        println!("{}", header.join("  ").trim_end());
+        for cells in &table {
+            let line: Vec<String> = cells
+                .iter()
+                .zip(&self.columns)
+                .zip(&widths)
+                .map(|((cell, field), width)| match field.kind() {
+                    // numbers are right aligned
+                    FieldKind::Integer
+                    | FieldKind::Duration
+                    | FieldKind::Length
+                    | FieldKind::Number => format!("{:>width$}", cell, width = width),
+                    _ => format!("{:<width$}", cell, width = width),
+                })
+                .collect();
+            println!("{}", line.join("  ").trim_end());
+        }
+        println!("\n{} activities", rows.len());
+    }
+
+    fn format_cell(field: Field, cell: &Cell) -> String {
+        match (field.kind(), cell) {
+            (_, Cell::Null) => "-".to_string(),
+            (_, Cell::Time(time)) => time.format("%Y-%m-%d %H:%M").to_string(),
+            (_, Cell::Integer(value)) => value.to_string(),
+            (_, Cell::Text(text)) => text.clone(),
+            (FieldKind::Duration, Cell::Real(seconds)) => {
+                let seconds = seconds.round() as i64;
+                format!(
+                    "{}:{:02}:{:02}",
+                    seconds / 3600,
+                    seconds % 3600 / 60,
+                    seconds % 60
+                )
+            }
+            (FieldKind::Length, Cell::Real(meters)) if field == Field::Distance => {
+                format!("{:.2} km", meters / 1000.0)
+            }
+            (FieldKind::Length, Cell::Real(meters)) => format!("{:.0} m", meters),
+            (_, Cell::Real(value)) => format!("{:.0}", value),
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use crate::config::Config;
+use crate::db::Database;
+use crate::output::OutputFormat;
+use crate::period::{PERIOD_HELP, Period};
+use crate::strength::{self, ExerciseVolume, MuscleGroupVolume, OneRepMax, StrengthSet};
+use crate::timestamps;
+use clap::Args;
+use rusqlite::types::Value;
+use serde::Serialize;
+
+#[derive(Debug, Args)]
+pub struct StrengthArgs {
+    #[arg(long, value_parser = Period::parse, default_value = "90d")]
+    #[arg(help = PERIOD_HELP)]
+    pub period: Period,
+
+    #[arg(long, help = "only include these exercises (bench_press, squat, ...)")]
+    pub exercise: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+struct StrengthReport<'a> {
+    period: String,
+    exercises: &'a [ExerciseVolume],
+    muscle_groups: &'a [MuscleGroupVolume],
+    progression: &'a [OneRepMax],
+}
+
+impl StrengthArgs {
+    pub fn run(&self, _config: &Config, db: &Database, format: OutputFormat) -> anyhow::Result<()> {
+        let sets = self.fetch_sets(db)?;
+        let exercises = strength::exercise_volumes(&sets);
+        let muscle_groups = strength::muscle_group_volumes(&exercises);
+        let progression = strength::progression(&sets);
+
+        let report = StrengthReport {
+            period: self.period.label(),
+            exercises: &exercises,
+            muscle_groups: &muscle_groups,
+            progression: &progression,
+        };
+        format.print_document(&report, &exercises, || {
+            Self::print_strength(&report);
+            Ok(())
+        })
+    }
+
+    fn print_strength(report: &StrengthReport) {
+        println!("Strength training, {}", report.period);
+        if report.exercises.is_empty() {
+            println!("\nNo strength sets in this period.");
+            return;
+        }
+
+        println!(
+            "\n{:<22} {:<12} {:>6} {:>6} {:>10} {:>10}",
+            "Exercise", "Muscles", "Sets", "Reps", "Volume", "Est. 1RM"
+        );
+        for exercise in report.exercises {
+            println!(
+                "{:<22} {:<12} {:>6} {:>6} {:>10} {:>10}",
+                exercise.exercise,
+                exercise.muscle_group,
+                exercise.sets,
+                exercise.repetitions,
+                format!("{:.0} kg", exercise.volume),
+                exercise
+                    .best_1rm
+                    .map(|estimate| format!("{:.1} kg", estimate))
+                    .unwrap_or_else(|| "N/A".to_string())
+            );
+        }
+
+        println!(
+            "\n{:<22} {:>6} {:>6} {:>10}",
+            "Muscle group", "Sets", "Reps", "Volume"
+        );
+        for group in report.muscle_groups {
+            println!(
+                "{:<22} {:>6} {:>6} {:>10}",
+                group.muscle_group,
+                group.sets,
+                group.repetitions,
+                format!("{:.0} kg", group.volume)
+            );
+        }
+
+        if report.progression.is_empty() {
+            return;
+        }
+        println!(
+            "\nEstimated 1RM progression (sets of up to {} reps):",
+            strength::MAX_1RM_REPS
+        );
+        let mut exercise = "";
+        for entry in report.progression {
+            if entry.exercise != exercise {
+                exercise = &entry.exercise;
+                println!("\n{}", exercise);
+            }
+            println!(
+                "  {}  {:>8}  ({:.1} kg x {}, {})",
+                entry.week,
+                format!("{:.1} kg", entry.estimate),
+                entry.weight,
+                entry.repetitions,
+                entry.timestamp.format("%Y-%m-%d")
+            );
+        }
+    }
+
+    // sets of all activities in the period, optionally of some exercises only
+    fn fetch_sets(&self, db: &Database) -> anyhow::Result<Vec<StrengthSet>> {
+        let (start, end) = self.period.epoch_bounds();
+        let mut conditions = vec![
+            "activities.timestamp + activities.utc_offset >= ? AND activities.timestamp + activities.utc_offset < ?"
+                .to_string(),
+        ];
+        let mut values = vec![Value::Integer(start), Value::Integer(end)];
+        if let Some(exercises) = &self.exercise {
+            conditions.push(format!(
+                "sets.exercise IN ({})",
+                vec!["?"; exercises.len()].join(", ")
+            ));
+            values.extend(exercises.iter().cloned().map(Value::Text));
+        }
+
+        let query = format!(
+            "SELECT activities.id, activities.timestamp, activities.utc_offset, sets.exercise, sets.repetitions, sets.weight FROM sets JOIN activities ON activities.id = sets.activity_id WHERE {} ORDER BY activities.timestamp, sets.set_index",
+            conditions.join(" AND ")
+        );
+
+        let mut stmt = db.connection().prepare(&query)?;
+        let sets = stmt
+            .query_map(rusqlite::params_from_iter(&values), |row| {
+                Ok(StrengthSet {
+                    activity_id: row.get(0)?,
+                    timestamp: timestamps::activity_time(row, 1, 2)?,
+                    exercise: row
+                        .get::<_, Option<String>>(3)?
+                        .unwrap_or_else(|| "unknown".to_string()),
+                    repetitions: row.get(4)?,
+                    weight: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(sets)
+    }
+}
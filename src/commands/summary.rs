@@ -1,8 +1,12 @@
 use crate::config::Config;
 use crate::db::Database;
-use chrono::DateTime;
+use crate::output::OutputFormat;
+use crate::period::{PERIOD_HELP, Period};
+use crate::summary::{Comparison, Metric, MetricDelta, Summary, SummaryRequest};
+use crate::swim::SwimSummary;
+use chrono::{Local, NaiveDate};
 use clap::{Args, Subcommand};
-use rusqlite::params;
+use std::io::{self, IsTerminal};
 
 #[derive(Debug, Args)]
 pub struct SummaryArgs {
@@ -15,8 +19,78 @@ pub struct SummarySubcommandArgs {
     #[arg(long)]
     pub activity: Option<Vec<String>>,
 
+    #[arg(
+        long,
+        help = "filter by sub-sport (trail, treadmill, indoor_cycling, ...)"
+    )]
+    pub sub_sport: Option<Vec<String>>,
+
     #[arg(short, long)]
     pub list: bool,
+
+    #[arg(
+        short,
+        long,
+        help = "include time in heart rate, power and pace zones (pace zones for running only)"
+    )]
+    pub zones: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "compare with the previous period or the same period last year"
+    )]
+    pub compare: Option<Comparison>,
+}
+
+#[derive(Debug, Args)]
+pub struct WeekArgs {
+    #[arg(value_name = "WEEKNUM-YEAR", value_parser = Period::parse_week)]
+    #[arg(help = "ISO week as WEEKNUM-YEAR or YYYY-Wnn")]
+    pub week: Option<Period>,
+
+    #[command(flatten)]
+    pub args: SummarySubcommandArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct MonthArgs {
+    #[arg(value_name = "MONTHNUM-YEAR", value_parser = Period::parse_month)]
+    #[arg(help = "month as MONTHNUM-YEAR or YYYY-MM")]
+    pub month: Option<Period>,
+
+    #[command(flatten)]
+    pub args: SummarySubcommandArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct YearArgs {
+    #[arg(value_name = "YEAR", value_parser = Period::parse_year)]
+    pub year: Option<Period>,
+
+    #[command(flatten)]
+    pub args: SummarySubcommandArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct RangeArgs {
+    #[arg(long, help = "first day of the range (YYYY-MM-DD)")]
+    pub from: NaiveDate,
+
+    #[arg(long, help = "last day of the range (YYYY-MM-DD), defaults to today")]
+    pub to: Option<NaiveDate>,
+
+    #[command(flatten)]
+    pub args: SummarySubcommandArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct PeriodArgs {
+    #[arg(value_parser = Period::parse, help = PERIOD_HELP)]
+    pub period: Period,
+
+    #[command(flatten)]
+    pub args: SummarySubcommandArgs,
 }
 
 #[derive(Debug, Subcommand)]
@@ -32,292 +106,265 @@ pub enum Actions {
     #[command(name = "365d")]
     #[command(about = "summarize statistics over the last 365 days")]
     ThreeSixFiveDays(SummarySubcommandArgs),
+
+    #[command(name = "week")]
+    #[command(about = "summarize statistics over a calendar week (defaults to this week)")]
+    Week(WeekArgs),
+
+    #[command(name = "month")]
+    #[command(about = "summarize statistics over a calendar month (defaults to this month)")]
+    Month(MonthArgs),
+
+    #[command(name = "year")]
+    #[command(about = "summarize statistics over a calendar year (defaults to this year)")]
+    Year(YearArgs),
+
+    #[command(name = "range")]
+    #[command(about = "summarize statistics between two dates (inclusive)")]
+    Range(RangeArgs),
+
+    #[command(name = "period")]
+    #[command(about = "summarize statistics over any period, e.g. 2026-Q2, 2026-W07 or 90d")]
+    Period(PeriodArgs),
 }
 
 impl SummaryArgs {
-    pub fn run(&self, config: &Config, db: &Database) -> anyhow::Result<()> {
-        match &self.actions {
-            Actions::SevenDays(args) => {
-                self.run_seven_days(config, db, args)?;
-            }
-            Actions::ThirtyDays(args) => {
-                self.run_thirty_days(config, db, args)?;
-            }
-            Actions::ThreeSixFiveDays(args) => {
-                self.run_threesixfive_days(config, db, args)?;
-            }
-        }
-        Ok(())
+    pub fn run(&self, _config: &Config, db: &Database, format: OutputFormat) -> anyhow::Result<()> {
+        let (period, args) = match &self.actions {
+            Actions::SevenDays(args) => (Period::LastDays(7), args),
+            Actions::ThirtyDays(args) => (Period::LastDays(30), args),
+            Actions::ThreeSixFiveDays(args) => (Period::LastDays(365), args),
+            Actions::Week(week) => (week.week.unwrap_or_else(Period::current_week), &week.args),
+            Actions::Month(month) => (
+                month.month.unwrap_or_else(Period::current_month),
+                &month.args,
+            ),
+            Actions::Year(year) => (year.year.unwrap_or_else(Period::current_year), &year.args),
+            Actions::Range(range) => (
+                Period::range(
+                    range.from,
+                    range.to.unwrap_or_else(|| Local::now().date_naive()),
+                )?,
+                &range.args,
+            ),
+            Actions::Period(period) => (period.period, &period.args),
+        };
+
+        Self::run_period(db, &period, args, format)
     }
 
-    pub fn run_seven_days(
-        &self,
-        config: &Config,
+    fn run_period(
         db: &Database,
+        period: &Period,
         args: &SummarySubcommandArgs,
+        format: OutputFormat,
     ) -> anyhow::Result<()> {
-        match &args.activity {
-            Some(activity) => {
-                println!("7-Day Summary for {:?}\n", activity);
-            }
-            None => println!("7-Day Summary\n"),
+        let summary = SummaryRequest {
+            period: *period,
+            sports: args.activity.as_deref(),
+            sub_sports: args.sub_sport.as_deref(),
+            metrics: &Metric::ALL,
+            list_activities: args.list,
+            zones: args.zones,
+            compare: args.compare,
         }
+        .run(db)?;
 
-        println!(
-            "Total Duration: {}",
-            Self::format_duration(Self::sum_duration_last_n_days(db, 7, args)?)
-        );
-        println!(
-            "Total Distance: {:.2} km",
-            Self::sum_distance_last_n_days(db, 7, args)? / 1000.0
-        );
-        println!(
-            "Average Calories: {:.2} kcal",
-            Self::avg_calories_last_n_days(db, 7, args)?
-        );
-
-        println!("\n\nActivity breakdown:\n");
-
-        for (sport, count) in Self::count_sports_last_n_days(db, 7, args)? {
-            println!("{}: {} times", sport, count);
-
-        }
+        let table = || {
+            Self::print_summary(&summary);
+            Ok(())
+        };
 
-        if args.list == true {
-            println!("\n\nActivies of the last 7 days\n");
-            let activity_list = Self::activites_last_n_days(db, 7, args)?;
-            for activity in activity_list {
-                println!("{}", activity);
+        // csv and ndjson print the activity list instead of the totals when listing
+        match &summary.activities {
+            Some(activities) if format != OutputFormat::Json => {
+                format.print_rows(activities, table)
             }
+            _ => format.print_document(&summary, &summary.rows(), table),
         }
-
-        Ok(())
     }
 
-    pub fn run_thirty_days(
-        &self,
-        config: &Config,
-        db: &Database,
-        args: &SummarySubcommandArgs,
-    ) -> anyhow::Result<()> {
-        match &args.activity {
+    fn print_summary(summary: &Summary) {
+        match &summary.sports {
             Some(activity) => {
-                println!("30-Day Summary for {:?}\n", activity);
+                println!("{} Summary for {:?}\n", summary.period, activity);
             }
-            None => println!("30-Day Summary\n"),
+            None => println!("{} Summary\n", summary.period),
+        }
+        if let Some(sub_sports) = &summary.sub_sports {
+            println!("Sub-sports: {:?}\n", sub_sports);
         }
 
-        println!(
-            "Total Duration: {}",
-            Self::format_duration(Self::sum_duration_last_n_days(db, 30, args)?)
-        );
-        println!(
-            "Total Distance: {:.2} km",
-            Self::sum_distance_last_n_days(db, 30, args)? / 1000.0
-        );
-        println!(
-            "Average Calories: {:.2} kcal",
-            Self::avg_calories_last_n_days(db, 30, args)?
-        );
+        match &summary.comparison {
+            Some(comparison) => {
+                println!("Compared with {}\n", comparison.period);
+                println!(
+                    "Activities: {} vs {}",
+                    summary.total.count, comparison.total.count
+                );
+                for delta in &comparison.deltas {
+                    let values = format!(
+                        "{}: {} vs {}",
+                        delta.metric.label(),
+                        Self::format_optional_metric(delta.metric, delta.current),
+                        Self::format_optional_metric(delta.metric, delta.previous)
+                    );
+                    match Self::format_delta(delta) {
+                        Some(change) => println!("{}, {}", values, change),
+                        None => println!("{}", values),
+                    }
+                }
+            }
+            None => {
+                for metric in &summary.metrics {
+                    println!(
+                        "{}: {}",
+                        metric.label(),
+                        Self::format_optional_metric(*metric, summary.total.get(*metric))
+                    );
+                }
+            }
+        }
 
         println!("\n\nActivity breakdown:\n");
 
-        for (sport, count) in Self::count_sports_last_n_days(db, 30, args)? {
-            println!("{}: {} times", sport, count);
+        for sport in &summary.by_sport {
+            println!("{}: {} times", sport.sport, sport.totals.count);
+        }
 
+        if let Some(swim) = &summary.swim {
+            println!("\n\nSwimming:\n");
+            Self::print_swim(swim);
         }
 
-        if args.list == true {
-            println!("\n\nActivies of the last 30 days\n");
-            let activity_list = Self::activites_last_n_days(db, 30, args)?;
-            for activity in activity_list {
-                println!("{}", activity);
+        if let Some(zones) = &summary.zones {
+            println!("\n\nTime in zones:\n");
+            if zones.is_empty() {
+                println!("No zone data, import records and set zones in the athlete profile.");
+            }
+            for zone_summary in zones {
+                let zones = zone_summary
+                    .zones
+                    .iter()
+                    .map(|z| format!("Z{} {:.0}%", z.zone, z.percent))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let (low, high) = zone_summary.polarization();
+                println!(
+                    "{} ({}): {}",
+                    zone_summary.kind.label(),
+                    Self::format_duration(zone_summary.total),
+                    zones
+                );
+                println!("    Z1-2: {:.0}%, Z3+: {:.0}%", low, high);
             }
         }
 
-        Ok(())
-    }
-
-    pub fn run_threesixfive_days(
-        &self,
-        config: &Config,
-        db: &Database,
-        args: &SummarySubcommandArgs,
-    ) -> anyhow::Result<()> {
-        match &args.activity {
-            Some(activity) => {
-                println!("365-Day Summary for {:?}\n", activity);
+        if let Some(activities) = &summary.activities {
+            println!("\n\nActivities of {}\n", summary.period);
+            for activity in activities {
+                let sport = match &activity.sub_sport {
+                    Some(sub_sport) if sub_sport != "generic" => {
+                        format!("{} ({})", activity.sport, sub_sport)
+                    }
+                    _ => activity.sport.clone(),
+                };
+                match &activity.workout {
+                    Some(workout) => println!(
+                        "{} - {} {} - {}",
+                        activity.timestamp.format("%Y-%m-%d"),
+                        Self::format_duration(activity.duration),
+                        sport,
+                        workout
+                    ),
+                    None => println!(
+                        "{} - {} {}",
+                        activity.timestamp.format("%Y-%m-%d"),
+                        Self::format_duration(activity.duration),
+                        sport
+                    ),
+                }
             }
-            None => println!("365-Day Summary\n"),
         }
+    }
 
+    pub fn print_swim(swim: &SwimSummary) {
+        println!("{:<12}: {:.0} m", "Distance", swim.distance);
         println!(
-            "Total Duration: {}",
-            Self::format_duration(Self::sum_duration_last_n_days(db, 365, args)?)
+            "{:<12}: {}",
+            "Swim time",
+            Self::format_duration(swim.swim_time)
         );
         println!(
-            "Total Distance: {:.2} km",
-            Self::sum_distance_last_n_days(db, 365, args)? / 1000.0
+            "{:<12}: {}",
+            "Pace",
+            swim.pace
+                .map(|pace| format!("{}:{:02} /100m", (pace / 60.0) as i64, (pace % 60.0) as i64))
+                .unwrap_or_else(|| "N/A".to_string())
         );
+        // open water swims have no lengths to take SWOLF, rest or strokes from
+        if swim.pool_swims == 0 {
+            return;
+        }
         println!(
-            "Average Calories: {:.2} kal",
-            Self::avg_calories_last_n_days(db, 365, args)?
+            "{:<12}: {}",
+            "Avg SWOLF",
+            swim.avg_swolf
+                .map(|swolf| format!("{:.0}", swolf))
+                .unwrap_or_else(|| "N/A".to_string())
         );
-
-        println!("\n\nActivity breakdown:\n");
-
-        for (sport, count) in Self::count_sports_last_n_days(db, 365, args)? {
-            println!("{}: {} times", sport, count);
-
+        println!(
+            "{:<12}: {} ({})",
+            "Rest",
+            swim.rest_intervals,
+            Self::format_duration(swim.rest_time)
+        );
+        for stroke in &swim.strokes {
+            println!("    {:<14} {:>6.0} m", stroke.stroke, stroke.distance);
         }
+    }
 
-        if args.list == true {
-            println!("\n\nActivies of the last 365 days\n");
-            let activity_list = Self::activites_last_n_days(db, 365, args)?;
-            for activity in activity_list {
-                println!("{}", activity);
-            }
+    fn format_metric(metric: Metric, value: f64) -> String {
+        match metric {
+            Metric::Duration => Self::format_duration(value),
+            Metric::Distance => format!("{:.2} km", value / 1000.0),
+            Metric::Calories => format!("{:.2} kcal", value),
         }
-
-        Ok(())
     }
 
-    fn sum_distance_last_n_days(
-        db: &Database,
-        days: u16,
-        args: &SummarySubcommandArgs,
-    ) -> anyhow::Result<f64> {
-        let base_query = "SELECT SUM(distance) FROM activities WHERE distance IS NOT NULL AND timestamp >= datetime('now', ?)";
-
-        let query = if let Some(activity_list) = &args.activity {
-            let activities_condition = activity_list
-                .iter()
-                .map(|a| format!("'{}'", a))
-                .collect::<Vec<_>>()
-                .join(", ");
-
-            format!("{} AND sport IN ({})", base_query, activities_condition)
-        } else {
-            base_query.to_string()
-        };
-
-        let sum_distance: Option<f64> =
-            db.connection()
-                .query_row(&query, params![format!("-{} days", days)], |row| row.get(0))?;
-
-        Ok(sum_distance.unwrap_or(0.0))
-
-        // let sum_distance: f64 = db.connection().query_row("SELECT SUM(distance) FROM activities WHERE distance IS NOT NULL AND timestamp >= datetime('now', ?)", params![format!("-{} days", days)], |row| row.get(0))?;
-        // Ok(sum_distance)
+    fn format_optional_metric(metric: Metric, value: Option<f64>) -> String {
+        value
+            .map(|value| Self::format_metric(metric, value))
+            .unwrap_or_else(|| "N/A".to_string())
     }
 
-    fn sum_duration_last_n_days(
-        db: &Database,
-        days: u16,
-        args: &SummarySubcommandArgs,
-    ) -> anyhow::Result<f64> {
-        let base_query =
-            "SELECT SUM(duration) FROM activities WHERE timestamp >= datetime('now', ?)";
-
-        let query = if let Some(activity_list) = &args.activity {
-            let activities_condition = activity_list
-                .iter()
-                .map(|a| format!("'{}'", a))
-                .collect::<Vec<_>>()
-                .join(", ");
-
-            format!("{} AND sport IN ({})", base_query, activities_condition)
-        } else {
-            base_query.to_string()
+    // signed change with its percentage, green when up and red when down on a terminal;
+    // None when either period has no value
+    fn format_delta(delta: &MetricDelta) -> Option<String> {
+        let change = delta.change?;
+        let sign = if change < 0.0 { "-" } else { "+" };
+        let text = format!(
+            "{}{}",
+            sign,
+            Self::format_metric(delta.metric, change.abs())
+        );
+        let text = match delta.percent {
+            Some(percent) => format!("{} ({:+.1}%)", text, percent),
+            None => text,
         };
 
-        let sum_duration: Option<f64> =
-            db.connection()
-                .query_row(&query, params![format!("-{} days", days)], |row| row.get(0))?;
-
-        Ok(sum_duration.unwrap_or(0.0))
-
-        // let sum_distance: f64 = db.connection().query_row("SELECT SUM(distance) FROM activities WHERE distance IS NOT NULL AND timestamp >= datetime('now', ?)", params![format!("-{} days", days)], |row| row.get(0))?;
-        // Ok(sum_distance)
-    }
-
-    fn avg_calories_last_n_days(
-        db: &Database,
-        days: u16,
-        args: &SummarySubcommandArgs,
-    ) -> anyhow::Result<f64> {
-        let base_query =
-            "SELECT AVG(calories) FROM activities WHERE timestamp >= datetime('now', ?)";
-
-        let query = if let Some(activity_list) = &args.activity {
-            let activities_condition = activity_list
-                .iter()
-                .map(|a| format!("'{}'", a))
-                .collect::<Vec<_>>()
-                .join(", ");
-
-            format!("{} AND sport IN ({})", base_query, activities_condition)
+        let color = if change > 0.0 {
+            "32"
+        } else if change < 0.0 {
+            "31"
         } else {
-            base_query.to_string()
+            return Some(text);
         };
-
-        let avg_calories: Option<f64> =
-            db.connection()
-                .query_row(&query, params![format!("-{} days", days)], |row| row.get(0))?;
-
-        Ok(avg_calories.unwrap_or(0.0))
-
-        // let sum_distance: f64 = db.connection().query_row("SELECT SUM(distance) FROM activities WHERE distance IS NOT NULL AND timestamp >= datetime('now', ?)", params![format!("-{} days", days)], |row| row.get(0))?;
-        // Ok(sum_distance)
-    }
-
-    fn activites_last_n_days(
-        db: &Database,
-        days: u16,
-        args: &SummarySubcommandArgs,
-    ) -> anyhow::Result<Vec<String>> {
-        let query = "SELECT timestamp, sport, duration FROM activities WHERE timestamp >= datetime('now', ?) ORDER BY timestamp DESC";
-
-        let mut stmt = db.connection().prepare(query)?;
-
-        let activities_iter = stmt.query_map(params![format!("-{} days", days)], |row| {
-            let timestamp: String = row.get(0)?;
-            let sport: String = row.get(1)?;
-            let duration: f64 = row.get(2)?;
-
-            // TODO: fix this error handling
-            let parsed_date =
-                DateTime::parse_from_rfc3339(&timestamp).expect("Failed to parse datetime");
-
-            Ok(format!(
-                "{} - {} {}",
-                parsed_date.format("%Y-%m-%d"),
-                Self::format_duration(duration),
-                sport
-            ))
-        })?;
-
-        let activities: Vec<String> = activities_iter.collect::<Result<Vec<String>, _>>()?;
-
-        Ok(activities)
-    }
-
-    fn count_sports_last_n_days(
-        db: &Database,
-        days: u16,
-        args: &SummarySubcommandArgs,
-    ) -> anyhow::Result<Vec<(String, i64)>> {
-        let query = "SELECT sport, COUNT(*) as sport_count FROM activities WHERE timestamp >= datetime('now', ?) GROUP BY sport ORDER BY sport_count DESC";
-
-        let mut stmt = db.connection().prepare(query)?;
-
-        let sports_count = stmt
-            .query_map(params![format!("-{} days", days)], |row| {
-                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
-            })?
-            .collect::<rusqlite::Result<Vec<(String, i64)>>>()?;
-
-        Ok(sports_count)
+        if io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none() {
+            Some(format!("\x1b[{}m{}\x1b[0m", color, text))
+        } else {
+            Some(text)
+        }
     }
 
     fn format_duration(seconds: f64) -> String {
@@ -326,10 +373,6 @@ impl SummaryArgs {
         let mins = (remaining_seconds / 60.0).floor() as u64;
         let secs = (remaining_seconds % 60.0).floor() as u64;
 
-        // if hours == 0 {
-        //     format!("{:02} m {:02} s", mins, secs)
-        // } else {
         format!("{:02} h {:02} m {:02} s", hours, mins, secs)
-        // }
     }
 }
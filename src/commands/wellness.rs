@@ -0,0 +1,131 @@
+use crate::config::Config;
+use crate::db::Database;
+use crate::output::OutputFormat;
+use crate::period::{PERIOD_HELP, Period};
+use crate::wellness::{self, DailyWellness, WellnessTrend};
+use clap::Args;
+use serde::Serialize;
+
+#[derive(Debug, Args)]
+pub struct WellnessArgs {
+    #[arg(long, value_parser = Period::parse, default_value = "30d")]
+    #[arg(help = PERIOD_HELP)]
+    pub period: Period,
+}
+
+#[derive(Debug, Serialize)]
+struct WellnessReport<'a> {
+    period: String,
+    days: &'a [DailyWellness],
+    trends: &'a [WellnessTrend],
+    // recent resting heart rate is RESTING_HR_ALERT bpm or more above its baseline
+    resting_hr_elevated: bool,
+}
+
+impl WellnessArgs {
+    pub fn run(&self, _config: &Config, db: &Database, format: OutputFormat) -> anyhow::Result<()> {
+        let (from, to) = self.period.date_bounds();
+        let days = DailyWellness::load(db.connection(), from, to)?;
+        let trends = WellnessTrend::compute(&days, to);
+        let resting_hr_elevated = trends
+            .iter()
+            .find(|trend| trend.metric == "resting_hr")
+            .and_then(|trend| trend.change())
+            .is_some_and(|change| change >= wellness::RESTING_HR_ALERT);
+
+        let report = WellnessReport {
+            period: self.period.label(),
+            days: &days,
+            trends: &trends,
+            resting_hr_elevated,
+        };
+        format.print_document(&report, &days, || {
+            Self::print_wellness(&report);
+            Ok(())
+        })
+    }
+
+    fn print_wellness(report: &WellnessReport) {
+        println!("Wellness, {}", report.period);
+        if report.days.is_empty() {
+            println!("\nNo wellness data, import monitoring, sleep and HRV .fit files.");
+            return;
+        }
+
+        println!(
+            "\n{:<10} {:>7} {:>5} {:>6} {:>6} {:>6} {:>6} {:>6} {:>9} {:>5}",
+            "Date", "Steps", "RHR", "Sleep", "Deep", "REM", "Awake", "Stress", "Battery", "HRV"
+        );
+        for day in report.days {
+            let battery = match (day.body_battery_min, day.body_battery_max) {
+                (Some(min), Some(max)) => format!("{:.0}-{:.0}", min, max),
+                _ => "-".to_string(),
+            };
+            println!(
+                "{:<10} {:>7} {:>5} {:>6} {:>6} {:>6} {:>6} {:>6} {:>9} {:>5}",
+                day.date,
+                day.steps
+                    .map(|steps| steps.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                Self::format_optional(day.resting_hr),
+                Self::format_hours(day.sleep),
+                Self::format_hours(day.sleep_deep),
+                Self::format_hours(day.sleep_rem),
+                Self::format_hours(day.sleep_awake),
+                Self::format_optional(day.avg_stress),
+                battery,
+                Self::format_optional(day.hrv)
+            );
+        }
+
+        println!(
+            "\n{:<12} {:>10} {:>10} {:>10} {:>8}",
+            "Trend", "Average", "Last 7d", "Before", "Change"
+        );
+        for trend in report.trends {
+            if trend.average.is_none() {
+                continue;
+            }
+            let format_value = |value: Option<f64>| match trend.metric.as_str() {
+                "sleep" => Self::format_hours(value),
+                _ => Self::format_optional(value),
+            };
+            let change = match (trend.metric.as_str(), trend.change()) {
+                ("sleep", Some(change)) => format!("{:+.0} min", change / 60.0),
+                (_, Some(change)) => format!("{:+.0}", change),
+                (_, None) => "-".to_string(),
+            };
+            println!(
+                "{:<12} {:>10} {:>10} {:>10} {:>8}",
+                trend.metric,
+                format_value(trend.average),
+                format_value(trend.recent),
+                format_value(trend.baseline),
+                change
+            );
+        }
+
+        if report.resting_hr_elevated {
+            println!(
+                "\nWarning: resting heart rate of the last {} days is {} bpm or more above the days before, a possible sign of fatigue or illness",
+                wellness::RECENT_DAYS,
+                wellness::RESTING_HR_ALERT
+            );
+        }
+    }
+
+    fn format_optional(value: Option<f64>) -> String {
+        value
+            .map(|value| format!("{:.0}", value))
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    fn format_hours(seconds: Option<f64>) -> String {
+        seconds
+            .map(|seconds| {
+                let minutes = (seconds / 60.0).round() as i64;
+                format!("{}:{:02}", minutes / 60, minutes % 60)
+            })
+            .unwrap_or_else(|| "-".to_string())
+    }
+}
@@ -1,13 +1,27 @@
+use crate::athlete::Athlete;
 use anyhow::Context;
-use dirs;
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
-use toml;
 
 #[derive(Deserialize, Serialize, Debug, Default)]
 pub struct Config {
     data: PathBuf,
+
+    // date-effective athlete profile entries
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    athlete: Vec<Athlete>,
+
+    #[serde(default)]
+    import: ImportConfig,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct ImportConfig {
+    // import per-record time series (heart rate, power, GPS, ...) into the streams table
+    #[serde(default)]
+    records: bool,
 }
 
 static DEFAULT_CONFIG: &str = r#"
@@ -111,4 +125,40 @@ impl Config {
     pub fn set_data_path(&mut self, data: PathBuf) {
         self.data = data;
     }
+
+    pub fn get_import_records(&self) -> bool {
+        self.import.records
+    }
+
+    pub fn set_import_records(&mut self, records: bool) {
+        self.import.records = records;
+    }
+
+    // athlete profile in effect on the given date
+    pub fn get_athlete_entries(&self) -> &[Athlete] {
+        &self.athlete
+    }
+
+    pub fn get_athlete(&self, date: NaiveDate) -> Athlete {
+        Athlete::effective(&self.athlete, date)
+    }
+
+    // athlete entry starting on `since` (None for the undated entry), created if missing
+    pub fn athlete_entry_mut(&mut self, since: Option<NaiveDate>) -> &mut Athlete {
+        let index = match self.athlete.iter().position(|entry| entry.since == since) {
+            Some(index) => index,
+            None => {
+                self.athlete.push(Athlete {
+                    since,
+                    ..Athlete::default()
+                });
+                self.athlete.sort_by_key(|entry| entry.since);
+                self.athlete
+                    .iter()
+                    .position(|entry| entry.since == since)
+                    .expect("athlete entry was just added")
+            }
+        };
+        &mut self.athlete[index]
+    }
 }
@@ -1,7 +1,382 @@
 use crate::VERSION;
 use anyhow::Context;
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, OptionalExtension, params};
 use std::cell::Cell;
+use std::path::PathBuf;
+
+pub static REIMPORT_MESSAGE: &str = "The database schema changed and existing activities need data from their .fit files.\nPlease run 'queryfit database recreate'.\nNo data will be lost.";
+
+// last app version whose database had no schema version
+static LEGACY_VERSION: &str = "v0.6.0";
+
+// one schema change; migrations run in order, each in its own transaction
+struct Migration {
+    version: i64,
+    description: &'static str,
+    sql: &'static str,
+    // existing rows would be wrong without data from the .fit files; new tables and columns
+    // that stay empty for already imported files until a recreate do not need one
+    reimport: bool,
+}
+
+static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "files, activities and devices",
+        sql: "
+            CREATE TABLE IF NOT EXISTS files (
+            filename TEXT PRIMARY KEY
+            );
+            CREATE TABLE IF NOT EXISTS activities (
+            id INTEGER PRIMARY KEY,
+            timestamp DATETIME NOT NULL,
+            sport TEXT NOT NULL,
+            duration REAL,
+            distance REAL,
+            avg_hr REAL,
+            calories REAL,
+            elevation REAL,
+            avg_power REAL,
+            rpe REAL,
+            rpe_est BOOL
+            );
+            CREATE TABLE IF NOT EXISTS devices (
+            id ITEGER PRIMARY KEY,
+            product TEXT NOT NULL,
+            timestamp DATETIME NOT NULL,
+            battery REAL,
+            battery_status TEXT
+            );
+        ",
+        reimport: false,
+    },
+    Migration {
+        version: 2,
+        description: "record streams, laps, time in zones and best efforts",
+        sql: "
+            -- one row per activity, every channel a blob of little-endian floats (f64 for lat/lon)
+            CREATE TABLE IF NOT EXISTS streams (
+            activity_id INTEGER PRIMARY KEY,
+            samples INTEGER NOT NULL,
+            time BLOB NOT NULL,
+            heart_rate BLOB,
+            power BLOB,
+            speed BLOB,
+            cadence BLOB,
+            altitude BLOB,
+            lat BLOB,
+            lon BLOB,
+            distance BLOB
+            );
+            CREATE TABLE IF NOT EXISTS laps (
+            id INTEGER PRIMARY KEY,
+            activity_id INTEGER NOT NULL,
+            lap INTEGER NOT NULL,
+            start_time DATETIME NOT NULL,
+            duration REAL,
+            distance REAL,
+            avg_hr REAL,
+            max_hr REAL,
+            avg_power REAL,
+            avg_cadence REAL,
+            lap_trigger TEXT
+            );
+            -- seconds per zone (1-based) of each zone kind (hr, power, pace)
+            CREATE TABLE IF NOT EXISTS activity_zones (
+            activity_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            zone INTEGER NOT NULL,
+            seconds REAL NOT NULL,
+            PRIMARY KEY (activity_id, kind, zone)
+            );
+            -- target is seconds for power/pace efforts and meters for distance efforts,
+            -- value is W for power and m/s for pace and distance
+            CREATE TABLE IF NOT EXISTS best_efforts (
+            activity_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            target REAL NOT NULL,
+            value REAL NOT NULL,
+            PRIMARY KEY (activity_id, kind, target)
+            );
+        ",
+        reimport: false,
+    },
+    Migration {
+        version: 3,
+        description: "import errors",
+        sql: "
+            -- .fit files that failed to import, skipped until retried
+            CREATE TABLE IF NOT EXISTS import_errors (
+            filename TEXT PRIMARY KEY,
+            path TEXT NOT NULL,
+            error TEXT NOT NULL,
+            app_version TEXT NOT NULL,
+            timestamp DATETIME NOT NULL
+            );
+        ",
+        reimport: false,
+    },
+    Migration {
+        version: 4,
+        description: "track files by content hash and link rows to their file",
+        sql: "
+            DROP TABLE IF EXISTS files;
+            CREATE TABLE files (
+            id INTEGER PRIMARY KEY,
+            filename TEXT NOT NULL,
+            path TEXT NOT NULL UNIQUE,
+            -- hex SHA-256 of the file contents
+            hash TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            -- unix seconds
+            mtime INTEGER NOT NULL
+            );
+            CREATE INDEX files_hash ON files (hash);
+
+            ALTER TABLE activities ADD COLUMN file_id INTEGER REFERENCES files (id) ON DELETE CASCADE;
+            CREATE INDEX activities_file_id ON activities (file_id);
+            ALTER TABLE devices ADD COLUMN file_id INTEGER REFERENCES files (id) ON DELETE CASCADE;
+            CREATE INDEX devices_file_id ON devices (file_id);
+
+            -- rebuild the per-activity tables so their rows are removed with the activity
+            CREATE TABLE streams_new (
+            activity_id INTEGER PRIMARY KEY REFERENCES activities (id) ON DELETE CASCADE,
+            samples INTEGER NOT NULL,
+            time BLOB NOT NULL,
+            heart_rate BLOB,
+            power BLOB,
+            speed BLOB,
+            cadence BLOB,
+            altitude BLOB,
+            lat BLOB,
+            lon BLOB,
+            distance BLOB
+            );
+            INSERT INTO streams_new SELECT * FROM streams;
+            DROP TABLE streams;
+            ALTER TABLE streams_new RENAME TO streams;
+
+            CREATE TABLE laps_new (
+            id INTEGER PRIMARY KEY,
+            activity_id INTEGER NOT NULL REFERENCES activities (id) ON DELETE CASCADE,
+            lap INTEGER NOT NULL,
+            start_time DATETIME NOT NULL,
+            duration REAL,
+            distance REAL,
+            avg_hr REAL,
+            max_hr REAL,
+            avg_power REAL,
+            avg_cadence REAL,
+            lap_trigger TEXT
+            );
+            INSERT INTO laps_new SELECT * FROM laps;
+            DROP TABLE laps;
+            ALTER TABLE laps_new RENAME TO laps;
+            CREATE INDEX laps_activity_id ON laps (activity_id);
+
+            CREATE TABLE activity_zones_new (
+            activity_id INTEGER NOT NULL REFERENCES activities (id) ON DELETE CASCADE,
+            kind TEXT NOT NULL,
+            zone INTEGER NOT NULL,
+            seconds REAL NOT NULL,
+            PRIMARY KEY (activity_id, kind, zone)
+            );
+            INSERT INTO activity_zones_new SELECT * FROM activity_zones;
+            DROP TABLE activity_zones;
+            ALTER TABLE activity_zones_new RENAME TO activity_zones;
+
+            CREATE TABLE best_efforts_new (
+            activity_id INTEGER NOT NULL REFERENCES activities (id) ON DELETE CASCADE,
+            kind TEXT NOT NULL,
+            target REAL NOT NULL,
+            value REAL NOT NULL,
+            PRIMARY KEY (activity_id, kind, target)
+            );
+            INSERT INTO best_efforts_new SELECT * FROM best_efforts;
+            DROP TABLE best_efforts;
+            ALTER TABLE best_efforts_new RENAME TO best_efforts;
+
+            -- failed files are identified by path, and retried once their size or mtime changes
+            DROP TABLE IF EXISTS import_errors;
+            CREATE TABLE import_errors (
+            path TEXT PRIMARY KEY,
+            filename TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            mtime INTEGER NOT NULL,
+            error TEXT NOT NULL,
+            app_version TEXT NOT NULL,
+            timestamp DATETIME NOT NULL
+            );
+        ",
+        reimport: true,
+    },
+    Migration {
+        version: 5,
+        description: "link activities to the devices used",
+        sql: "
+            -- give devices a rowid primary key so they can be referenced
+            CREATE TABLE devices_new (
+            id INTEGER PRIMARY KEY,
+            product TEXT NOT NULL,
+            timestamp DATETIME NOT NULL,
+            battery REAL,
+            battery_status TEXT,
+            file_id INTEGER REFERENCES files (id) ON DELETE CASCADE
+            );
+            INSERT INTO devices_new (id, product, timestamp, battery, battery_status, file_id)
+            SELECT rowid, product, timestamp, battery, battery_status, file_id FROM devices;
+            DROP TABLE devices;
+            ALTER TABLE devices_new RENAME TO devices;
+            CREATE INDEX devices_file_id ON devices (file_id);
+
+            -- every device recorded in a file was used for every activity in it
+            CREATE TABLE activity_devices (
+            activity_id INTEGER NOT NULL REFERENCES activities (id) ON DELETE CASCADE,
+            device_id INTEGER NOT NULL REFERENCES devices (id) ON DELETE CASCADE,
+            PRIMARY KEY (activity_id, device_id)
+            );
+            CREATE INDEX activity_devices_device_id ON activity_devices (device_id);
+            INSERT INTO activity_devices (activity_id, device_id)
+            SELECT activities.id, devices.id
+            FROM activities JOIN devices ON devices.file_id = activities.file_id;
+        ",
+        reimport: false,
+    },
+    Migration {
+        version: 6,
+        description: "store timestamps as UTC epoch seconds with the activity's UTC offset",
+        sql: "
+            -- offsets are taken from the stored RFC3339 text, a recreate replaces them with the
+            -- offsets recorded in the .fit files
+            ALTER TABLE activities ADD COLUMN utc_offset INTEGER NOT NULL DEFAULT 0;
+            UPDATE activities SET
+            utc_offset = CASE
+                WHEN timestamp LIKE '%Z' THEN 0
+                ELSE (CASE substr(timestamp, -6, 1) WHEN '-' THEN -1 ELSE 1 END)
+                    * (CAST(substr(timestamp, -5, 2) AS INTEGER) * 3600
+                    + CAST(substr(timestamp, -2, 2) AS INTEGER) * 60)
+            END,
+            timestamp = CAST(strftime('%s', timestamp) AS INTEGER);
+            CREATE INDEX activities_timestamp ON activities (timestamp);
+
+            UPDATE laps SET start_time = CAST(strftime('%s', start_time) AS INTEGER);
+            UPDATE devices SET timestamp = CAST(strftime('%s', timestamp) AS INTEGER);
+            UPDATE import_errors SET timestamp = CAST(strftime('%s', timestamp) AS INTEGER);
+        ",
+        reimport: false,
+    },
+    Migration {
+        version: 7,
+        description: "sub-sport, sport profile and workout name of activities",
+        sql: "
+            ALTER TABLE activities ADD COLUMN sub_sport TEXT;
+            ALTER TABLE activities ADD COLUMN profile TEXT;
+            ALTER TABLE activities ADD COLUMN workout TEXT;
+        ",
+        reimport: false,
+    },
+    Migration {
+        version: 8,
+        description: "strength training sets",
+        sql: "
+            CREATE TABLE sets (
+            id INTEGER PRIMARY KEY,
+            activity_id INTEGER NOT NULL REFERENCES activities (id) ON DELETE CASCADE,
+            set_index INTEGER NOT NULL,
+            start_time INTEGER NOT NULL,
+            duration REAL,
+            repetitions INTEGER,
+            weight REAL,
+            exercise TEXT
+            );
+            CREATE INDEX sets_activity_id ON sets (activity_id);
+        ",
+        reimport: false,
+    },
+    Migration {
+        version: 9,
+        description: "pool length and swimming lengths",
+        sql: "
+            ALTER TABLE activities ADD COLUMN pool_length REAL;
+            CREATE TABLE lengths (
+            id INTEGER PRIMARY KEY,
+            activity_id INTEGER NOT NULL REFERENCES activities (id) ON DELETE CASCADE,
+            length INTEGER NOT NULL,
+            start_time INTEGER NOT NULL,
+            duration REAL,
+            strokes INTEGER,
+            stroke TEXT,
+            active BOOLEAN NOT NULL
+            );
+            CREATE INDEX lengths_activity_id ON lengths (activity_id);
+        ",
+        reimport: false,
+    },
+    Migration {
+        version: 10,
+        description: "daily wellness from monitoring, sleep, stress and HRV files",
+        sql: "
+            CREATE TABLE daily_wellness (
+            id INTEGER PRIMARY KEY,
+            file_id INTEGER NOT NULL REFERENCES files (id) ON DELETE CASCADE,
+            date TEXT NOT NULL,
+            steps INTEGER,
+            resting_hr REAL,
+            sleep_deep REAL,
+            sleep_light REAL,
+            sleep_rem REAL,
+            sleep_awake REAL,
+            stress_total REAL,
+            stress_samples INTEGER,
+            body_battery_min REAL,
+            body_battery_max REAL,
+            hrv_last_night REAL,
+            hrv_weekly REAL,
+            hrv_status TEXT
+            );
+            CREATE INDEX daily_wellness_date ON daily_wellness (date);
+        ",
+        reimport: false,
+    },
+    Migration {
+        version: 11,
+        description: "body metrics from weight scales",
+        sql: "
+            CREATE TABLE body_metrics (
+            id INTEGER PRIMARY KEY,
+            file_id INTEGER NOT NULL REFERENCES files (id) ON DELETE CASCADE,
+            timestamp INTEGER NOT NULL,
+            user_index INTEGER,
+            weight REAL,
+            body_fat REAL,
+            muscle_mass REAL,
+            bone_mass REAL,
+            hydration REAL,
+            bmi REAL
+            );
+            CREATE INDEX body_metrics_timestamp ON body_metrics (timestamp);
+        ",
+        reimport: false,
+    },
+    Migration {
+        version: 12,
+        description: "time of the HRV status of wellness days",
+        sql: "
+            -- unix seconds of the HRV summary the status was read from
+            ALTER TABLE daily_wellness ADD COLUMN hrv_timestamp INTEGER;
+        ",
+        reimport: false,
+    },
+    Migration {
+        version: 13,
+        description: "skipped duplicate files",
+        sql: "
+            -- 1 for a file with the same contents as an imported one, it has no rows of its own
+            ALTER TABLE files ADD COLUMN duplicate INTEGER NOT NULL DEFAULT 0;
+        ",
+        reimport: false,
+    },
+];
 
 #[derive(Debug)]
 pub struct Database {
@@ -10,11 +385,17 @@ pub struct Database {
 }
 
 impl Database {
+    pub fn path(config: &crate::config::Config) -> PathBuf {
+        config.get_data_path().join("database.db")
+    }
+
     pub fn new(config: &crate::config::Config) -> anyhow::Result<Self> {
-        let db_path = config.get_data_path().join("database.db");
+        let db_path = Self::path(config);
 
         let connection =
             Connection::open(&db_path).context("Failed to open/create database connetion")?;
+        // deleting a file row removes everything imported from it
+        connection.pragma_update(None, "foreign_keys", true)?;
 
         Ok(Self {
             conn: connection,
@@ -29,10 +410,13 @@ impl Database {
             .query_map([], |row| row.get(0))?
             .collect::<Result<Vec<String>, rusqlite::Error>>()?;
 
+        // drop tables in any order without cascading deletes
+        self.conn.pragma_update(None, "foreign_keys", false)?;
         for table in tables {
             self.conn
                 .execute(&format!("DROP TABLE IF EXISTS {}", table), [])?;
         }
+        self.conn.pragma_update(None, "foreign_keys", true)?;
 
         Ok(())
     }
@@ -47,98 +431,149 @@ impl Database {
         Ok(exists)
     }
 
-    pub fn correct_version(&self) -> anyhow::Result<bool> {
-        let db_version: String = self
+    pub fn schema_version(&self) -> anyhow::Result<Option<i64>> {
+        let version: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT value FROM metadata WHERE key = 'schema_version'",
+                params![],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Could not retrieve schema version from metadata table")?;
+
+        version
+            .map(|v| {
+                v.parse()
+                    .context("Invalid schema version in metadata table")
+            })
+            .transpose()
+    }
+
+    // true if the database has every migration applied and needs no reimport
+    pub fn is_current(&self) -> anyhow::Result<bool> {
+        let latest = MIGRATIONS.last().map_or(0, |migration| migration.version);
+        Ok(self.schema_version()? == Some(latest) && !self.reimport_required()?)
+    }
+
+    // true if a migration changed what gets imported and the files must be imported again
+    pub fn reimport_required(&self) -> anyhow::Result<bool> {
+        let required: Option<String> = self
             .conn
             .query_row(
-                "SELECT value FROM metadata WHERE key = 'version'",
+                "SELECT value FROM metadata WHERE key = 'reimport_required'",
                 params![],
                 |row| row.get(0),
             )
-            .context("Could not retrieve version from metadata table")?;
+            .optional()?;
 
-        Ok(db_version == VERSION)
+        Ok(required.is_some())
     }
 
     pub fn init_database(&self) -> anyhow::Result<()> {
         self.init_metadata_table()?;
-        self.init_files_table()?;
-        self.init_activities_table()?;
-        self.init_devices_table()?;
+        self.set_schema_version(0)?;
+        // nothing to import again in a new database
+        self.apply_migrations(false)?;
 
         Ok(())
     }
 
-    fn init_metadata_table(&self) -> anyhow::Result<()> {
-        self.conn
-            .execute(
-                "CREATE TABLE IF NOT EXISTS metadata (
-                key TEXT PRIMARY KEY,
-                value TEXT NOT NULL
-                )",
-                params![],
-            )
-            .context("Failed to create metadata table")?;
+    // bring an existing database up to the latest schema
+    pub fn migrate(&self) -> anyhow::Result<()> {
+        if self.schema_version()?.is_none() {
+            let version: Option<String> = self
+                .conn
+                .query_row(
+                    "SELECT value FROM metadata WHERE key = 'version'",
+                    params![],
+                    |row| row.get(0),
+                )
+                .optional()?;
 
+            // databases from before schema versioning only have a known layout as of LEGACY_VERSION
+            if version.as_deref() == Some(LEGACY_VERSION) {
+                self.set_schema_version(1)?;
+            } else {
+                self.set_reimport_required()?;
+                return Ok(());
+            }
+        }
+
+        self.apply_migrations(true)?;
         self.conn.execute(
             "INSERT OR REPLACE INTO metadata (key, value) VALUES ('version', ?1)",
             params![VERSION.to_string()],
         )?;
+
         Ok(())
     }
 
-    fn init_files_table(&self) -> anyhow::Result<()> {
-        self.conn
-            .execute(
-                "CREATE TABLE IF NOT EXISTS files (
-                filename TEXT PRIMARY KEY
-                )",
-                params![],
-            )
-            .context("Failed to create files table")?;
+    fn apply_migrations(&self, existing_data: bool) -> anyhow::Result<()> {
+        let current = self.schema_version()?.unwrap_or(0);
+
+        for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+            if existing_data {
+                eprintln!(
+                    "Migrating database to schema version {}: {}",
+                    migration.version, migration.description
+                );
+            }
+
+            let tx = self.conn.unchecked_transaction()?;
+            tx.execute_batch(migration.sql).with_context(|| {
+                format!(
+                    "Failed to migrate database to schema version {}",
+                    migration.version
+                )
+            })?;
+            tx.execute(
+                "INSERT OR REPLACE INTO metadata (key, value) VALUES ('schema_version', ?1)",
+                params![migration.version.to_string()],
+            )?;
+            if migration.reimport && existing_data {
+                tx.execute(
+                    "INSERT OR REPLACE INTO metadata (key, value) VALUES ('reimport_required', '1')",
+                    params![],
+                )?;
+            }
+            tx.commit()?;
+        }
 
         Ok(())
     }
 
-    fn init_activities_table(&self) -> anyhow::Result<()> {
-        self.conn
-            .execute(
-                "CREATE TABLE IF NOT EXISTS activities (
-                id INTEGER PRIMARY KEY,
-                timestamp DATETIME NOT NULL,
-                sport TEXT NOT NULL,
-                duration REAL,
-                distance REAL,
-                avg_hr REAL,
-                calories REAL,
-                elevation REAL,
-                avg_power REAL,
-                rpe REAL,
-                rpe_est BOOL
-                )
-                ",
-                params![],
-            )
-            .context("Failed to create activites table")?;
+    fn set_schema_version(&self, version: i64) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES ('schema_version', ?1)",
+            params![version.to_string()],
+        )?;
+        Ok(())
+    }
 
+    fn set_reimport_required(&self) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES ('reimport_required', '1')",
+            params![],
+        )?;
         Ok(())
     }
 
-    fn init_devices_table(&self) -> anyhow::Result<()> {
+    fn init_metadata_table(&self) -> anyhow::Result<()> {
         self.conn
             .execute(
-                "CREATE TABLE IF NOT EXISTS devices (
-                id ITEGER PRIMARY KEY,
-                product TEXT NOT NULL,
-                timestamp DATETIME NOT NULL,
-                battery REAL,
-                battery_status TEXT
-                )
-                ",
+                "CREATE TABLE IF NOT EXISTS metadata (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+                )",
                 params![],
             )
-            .context("Failed to create devices table")?;
+            .context("Failed to create metadata table")?;
 
+        self.conn.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES ('version', ?1)",
+            params![VERSION.to_string()],
+        )?;
         Ok(())
     }
 
@@ -0,0 +1,331 @@
+use crate::streams::{MAX_SAMPLE_GAP, Stream};
+use rusqlite::{Connection, params};
+use serde::Serialize;
+
+// durations (seconds) of the mean-maximal power and pace curves
+pub const CURVE_DURATIONS: [f64; 11] = [
+    5.0, 15.0, 30.0, 60.0, 120.0, 180.0, 300.0, 600.0, 1200.0, 1800.0, 3600.0,
+];
+
+// running distances (meters) of the best-distance efforts
+pub const BEST_DISTANCES: [(f64, &str); 4] = [
+    (1000.0, "1k"),
+    (5000.0, "5k"),
+    (10000.0, "10k"),
+    (21097.5, "half"),
+];
+
+// effort durations (seconds) used to fit critical power and critical speed
+const FIT_MIN_DURATION: f64 = 120.0;
+const FIT_MAX_DURATION: f64 = 1200.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EffortKind {
+    // best average power over a duration, in W
+    Power,
+    // best average running speed over a duration, in m/s
+    Pace,
+    // best average running speed over a distance, in m/s
+    Distance,
+}
+
+impl EffortKind {
+    pub const ALL: [EffortKind; 3] = [EffortKind::Power, EffortKind::Pace, EffortKind::Distance];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EffortKind::Power => "power",
+            EffortKind::Pace => "pace",
+            EffortKind::Distance => "distance",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+// best effort of one activity; higher values are always better
+#[derive(Debug, Clone)]
+pub struct BestEffort {
+    pub kind: EffortKind,
+    // seconds for power and pace, meters for distance
+    pub target: f64,
+    pub value: f64,
+}
+
+impl BestEffort {
+    pub fn compute(stream: &Stream, sport: &str) -> Vec<BestEffort> {
+        let mut efforts = Vec::new();
+
+        if let Some(power) = &stream.power {
+            let power = resample(&stream.time, power);
+            efforts.extend(CURVE_DURATIONS.iter().filter_map(|duration| {
+                Some(BestEffort {
+                    kind: EffortKind::Power,
+                    target: *duration,
+                    value: mean_max(&power, *duration as usize)?,
+                })
+            }));
+        }
+
+        if sport != "running" {
+            return efforts;
+        }
+
+        if let Some(speed) = &stream.speed {
+            let speed = resample(&stream.time, speed);
+            efforts.extend(CURVE_DURATIONS.iter().filter_map(|duration| {
+                Some(BestEffort {
+                    kind: EffortKind::Pace,
+                    target: *duration,
+                    value: mean_max(&speed, *duration as usize)?,
+                })
+            }));
+        }
+
+        if let Some(distance) = &stream.distance {
+            efforts.extend(BEST_DISTANCES.iter().filter_map(|(target, _)| {
+                let seconds = fastest_distance(&stream.time, distance, *target)?;
+                Some(BestEffort {
+                    kind: EffortKind::Distance,
+                    target: *target,
+                    value: target / seconds,
+                })
+            }));
+        }
+
+        efforts
+    }
+
+    // replace the stored best efforts of an activity
+    pub fn save_all(
+        conn: &Connection,
+        activity_id: i64,
+        efforts: &[BestEffort],
+    ) -> anyhow::Result<()> {
+        conn.execute(
+            "DELETE FROM best_efforts WHERE activity_id = ?1",
+            params![activity_id],
+        )?;
+
+        let mut stmt = conn.prepare(
+            "INSERT INTO best_efforts (activity_id, kind, target, value) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for effort in efforts {
+            stmt.execute(params![
+                activity_id,
+                effort.kind.name(),
+                effort.target,
+                effort.value
+            ])?;
+        }
+
+        Ok(())
+    }
+}
+
+// two-parameter critical power (W') or critical speed (D') model fitted to best efforts
+#[derive(Debug, Clone, Serialize)]
+pub struct CriticalFit {
+    // W or m/s
+    pub critical: f64,
+    // J or m
+    pub reserve: f64,
+    pub efforts: usize,
+}
+
+impl CriticalFit {
+    // linear fit of work (or distance) against duration over 2-20 minute efforts,
+    // None with fewer than two efforts or a non-physical result
+    pub fn fit(efforts: &[(f64, f64)]) -> Option<Self> {
+        let points: Vec<(f64, f64)> = efforts
+            .iter()
+            .filter(|(duration, _)| (FIT_MIN_DURATION..=FIT_MAX_DURATION).contains(duration))
+            .map(|(duration, average)| (*duration, average * duration))
+            .collect();
+        if points.len() < 2 {
+            return None;
+        }
+
+        let n = points.len() as f64;
+        let mean_t = points.iter().map(|(t, _)| t).sum::<f64>() / n;
+        let mean_w = points.iter().map(|(_, w)| w).sum::<f64>() / n;
+        let covariance: f64 = points
+            .iter()
+            .map(|(t, w)| (t - mean_t) * (w - mean_w))
+            .sum();
+        let variance: f64 = points.iter().map(|(t, _)| (t - mean_t).powi(2)).sum();
+        if variance <= 0.0 {
+            return None;
+        }
+
+        let critical = covariance / variance;
+        let reserve = mean_w - critical * mean_t;
+        if critical <= 0.0 || reserve < 0.0 {
+            return None;
+        }
+
+        Some(Self {
+            critical,
+            reserve,
+            efforts: points.len(),
+        })
+    }
+}
+
+// one value per second, holding each sample until the next one; gaps count as zero
+fn resample(time: &[f32], values: &[f32]) -> Vec<f64> {
+    let Some(last) = time.last() else {
+        return Vec::new();
+    };
+
+    let mut resampled = Vec::with_capacity(*last as usize + 1);
+    let mut index = 0;
+    for second in 0..=(*last as usize) {
+        let second = second as f32;
+        while index + 1 < time.len() && time[index + 1] <= second {
+            index += 1;
+        }
+        let value = values[index];
+        if second - time[index] <= MAX_SAMPLE_GAP && !value.is_nan() {
+            resampled.push(value as f64);
+        } else {
+            resampled.push(0.0);
+        }
+    }
+    resampled
+}
+
+// highest average over any window of `window` seconds
+fn mean_max(values: &[f64], window: usize) -> Option<f64> {
+    if window == 0 || values.len() < window {
+        return None;
+    }
+
+    let mut sum: f64 = values[..window].iter().sum();
+    let mut best = sum;
+    for i in window..values.len() {
+        sum += values[i] - values[i - window];
+        best = best.max(sum);
+    }
+    Some(best / window as f64)
+}
+
+// shortest time (seconds) in which the cumulative distance grew by `target` meters
+fn fastest_distance(time: &[f32], distance: &[f32], target: f64) -> Option<f64> {
+    let samples: Vec<(f64, f64)> = time
+        .iter()
+        .zip(distance)
+        .filter(|(_, d)| !d.is_nan())
+        .map(|(t, d)| (*t as f64, *d as f64))
+        .collect();
+
+    let mut best: Option<f64> = None;
+    let mut start = 0;
+    for end in 0..samples.len() {
+        while start + 1 < end && samples[end].1 - samples[start + 1].1 >= target {
+            start += 1;
+        }
+        if samples[end].1 - samples[start].1 >= target {
+            let seconds = samples[end].0 - samples[start].0;
+            if seconds > 0.0 {
+                best = Some(best.map_or(seconds, |b: f64| b.min(seconds)));
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one sample per second for ten minutes
+    fn stream() -> Stream {
+        Stream {
+            time: (0..600).map(|t| t as f32).collect(),
+            ..Stream::default()
+        }
+    }
+
+    fn values(efforts: &[BestEffort], kind: EffortKind) -> Vec<(f64, f64)> {
+        efforts
+            .iter()
+            .filter(|effort| effort.kind == kind)
+            .map(|effort| (effort.target, effort.value))
+            .collect()
+    }
+
+    #[test]
+    fn best_power_over_each_duration() {
+        // 200 W with 30 seconds at 400 W
+        let power = (0..600)
+            .map(|t| {
+                if (100..130).contains(&t) {
+                    400.0
+                } else {
+                    200.0
+                }
+            })
+            .collect();
+        let stream = Stream {
+            power: Some(power),
+            speed: Some(vec![3.0; 600]),
+            ..stream()
+        };
+
+        // the activity is shorter than the 20, 30 and 60 minute durations
+        let efforts = BestEffort::compute(&stream, "cycling");
+        let expected: Vec<(f64, f64)> = [
+            (5.0, 400.0),
+            (15.0, 400.0),
+            (30.0, 400.0),
+            (60.0, 300.0),
+            (120.0, 250.0),
+            (180.0, (30.0 * 400.0 + 150.0 * 200.0) / 180.0),
+            (300.0, 220.0),
+            (600.0, 210.0),
+        ]
+        .into();
+        let power = values(&efforts, EffortKind::Power);
+        assert_eq!(power.len(), expected.len());
+        for ((target, value), (expected_target, expected_value)) in power.iter().zip(&expected) {
+            assert_eq!(target, expected_target);
+            assert!((value - expected_value).abs() < 1e-9, "{} s", target);
+        }
+        // pace and distance efforts are only kept for running
+        assert!(
+            efforts
+                .iter()
+                .all(|effort| effort.kind == EffortKind::Power)
+        );
+    }
+
+    #[test]
+    fn best_running_pace_and_distance() {
+        // 3 m/s with 10 seconds at 5 m/s, and the distance covered at 3 m/s
+        let speed = (0..600)
+            .map(|t| if (200..210).contains(&t) { 5.0 } else { 3.0 })
+            .collect();
+        let stream = Stream {
+            speed: Some(speed),
+            distance: Some((0..600).map(|t| t as f32 * 3.0).collect()),
+            ..stream()
+        };
+
+        let efforts = BestEffort::compute(&stream, "running");
+        let pace = values(&efforts, EffortKind::Pace);
+        assert_eq!(
+            &pace[..3],
+            &[(5.0, 5.0), (15.0, 65.0 / 15.0), (30.0, 110.0 / 30.0)]
+        );
+        assert_eq!(pace.last(), Some(&(600.0, 1820.0 / 600.0)));
+        // 1000 m takes 334 whole seconds, 5000 m is longer than the run
+        assert_eq!(
+            values(&efforts, EffortKind::Distance),
+            vec![(1000.0, 1000.0 / 334.0)]
+        );
+    }
+}
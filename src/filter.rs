@@ -0,0 +1,799 @@
+use crate::period::Period;
+use anyhow::{Context, anyhow};
+use rusqlite::types::Value;
+
+// typed filter expressions over activity columns, compiled to parameterized SQL, e.g.
+// `sport = running and distance > 10km and date in 2026-Q2 and avg_hr < 150`
+//
+//   expression := term ("or" term)*
+//   term       := factor ("and" factor)*
+//   factor     := "not" factor | "(" expression ")" | FIELD OP VALUE | FIELD "in" list-or-period
+//
+// values are checked against the field type: lengths need a unit (m, km, mi), durations are
+// given as 45min, 1h, 90s or h:mm:ss, and dates as any period spec (see Period::parse)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Id,
+    Date,
+    Sport,
+    SubSport,
+    Workout,
+    Profile,
+    Duration,
+    Distance,
+    Elevation,
+    AvgHr,
+    AvgPower,
+    Calories,
+    Rpe,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Integer,
+    // compared by the activity's local time
+    Date,
+    Text,
+    // in seconds
+    Duration,
+    // in meters
+    Length,
+    Number,
+}
+
+impl Field {
+    pub const ALL: [Field; 13] = [
+        Field::Id,
+        Field::Date,
+        Field::Sport,
+        Field::SubSport,
+        Field::Workout,
+        Field::Profile,
+        Field::Duration,
+        Field::Distance,
+        Field::Elevation,
+        Field::AvgHr,
+        Field::AvgPower,
+        Field::Calories,
+        Field::Rpe,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Field::Id => "id",
+            Field::Date => "date",
+            Field::Sport => "sport",
+            Field::SubSport => "sub_sport",
+            Field::Workout => "workout",
+            Field::Profile => "profile",
+            Field::Duration => "duration",
+            Field::Distance => "distance",
+            Field::Elevation => "elevation",
+            Field::AvgHr => "avg_hr",
+            Field::AvgPower => "avg_power",
+            Field::Calories => "calories",
+            Field::Rpe => "rpe",
+        }
+    }
+
+    pub fn kind(&self) -> FieldKind {
+        match self {
+            Field::Id => FieldKind::Integer,
+            Field::Date => FieldKind::Date,
+            Field::Sport | Field::SubSport | Field::Workout | Field::Profile => FieldKind::Text,
+            Field::Duration => FieldKind::Duration,
+            Field::Distance | Field::Elevation => FieldKind::Length,
+            Field::AvgHr | Field::AvgPower | Field::Calories | Field::Rpe => FieldKind::Number,
+        }
+    }
+
+    // SQL expression of the field on the activities table
+    pub fn column(&self) -> &'static str {
+        match self {
+            Field::Date => "(activities.timestamp + activities.utc_offset)",
+            Field::Id => "activities.id",
+            Field::Sport => "activities.sport",
+            Field::SubSport => "activities.sub_sport",
+            Field::Workout => "activities.workout",
+            Field::Profile => "activities.profile",
+            Field::Duration => "activities.duration",
+            Field::Distance => "activities.distance",
+            Field::Elevation => "activities.elevation",
+            Field::AvgHr => "activities.avg_hr",
+            Field::AvgPower => "activities.avg_power",
+            Field::Calories => "activities.calories",
+            Field::Rpe => "activities.rpe",
+        }
+    }
+
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|field| field.name() == value.trim())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown field {:?}, expected one of: {}",
+                    value,
+                    Self::ALL.map(|field| field.name()).join(", ")
+                )
+            })
+    }
+}
+
+// a compiled WHERE condition and the values bound to its `?` placeholders
+#[derive(Debug, Clone)]
+pub struct Condition {
+    pub sql: String,
+    pub values: Vec<Value>,
+}
+
+impl Default for Condition {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl Condition {
+    // matches every activity
+    pub fn all() -> Self {
+        Condition {
+            sql: "1".to_string(),
+            values: Vec::new(),
+        }
+    }
+
+    pub fn new(sql: impl Into<String>, values: Vec<Value>) -> Self {
+        Condition {
+            sql: sql.into(),
+            values,
+        }
+    }
+
+    pub fn parse(expression: &str) -> anyhow::Result<Self> {
+        let tokens = tokenize(expression)?;
+        if tokens.is_empty() {
+            return Ok(Self::all());
+        }
+
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+        let condition = parser.expression()?;
+        if let Some(token) = parser.peek() {
+            return Err(anyhow!("Unexpected {:?} in filter", token.text()));
+        }
+        Ok(condition)
+    }
+
+    // activities of a period by their own local time, see Period::epoch_bounds
+    pub fn period(period: &Period) -> Self {
+        let (start, end) = period.epoch_bounds();
+        let column = Field::Date.column();
+        Condition::new(
+            format!("{column} >= ? AND {column} < ?"),
+            vec![Value::Integer(start), Value::Integer(end)],
+        )
+    }
+
+    // activities whose text field is one of `values`, ignoring case; sports may be aliases
+    pub fn any_of(field: Field, values: &[String]) -> Self {
+        debug_assert_eq!(field.kind(), FieldKind::Text);
+        if values.is_empty() {
+            return Self::all();
+        }
+        Condition::new(
+            format!(
+                "{} COLLATE NOCASE IN ({})",
+                field.column(),
+                vec!["?"; values.len()].join(", ")
+            ),
+            values
+                .iter()
+                .map(|value| match field {
+                    Field::Sport => Value::Text(sport_name(value)),
+                    _ => Value::Text(value.clone()),
+                })
+                .collect(),
+        )
+    }
+
+    pub fn and(self, other: Condition) -> Condition {
+        let mut values = self.values;
+        values.extend(other.values);
+        Condition {
+            sql: format!("({}) AND ({})", self.sql, other.sql),
+            values,
+        }
+    }
+
+    fn or(self, other: Condition) -> Condition {
+        let mut values = self.values;
+        values.extend(other.values);
+        Condition {
+            sql: format!("({}) OR ({})", self.sql, other.sql),
+            values,
+        }
+    }
+
+    fn not(self) -> Condition {
+        Condition {
+            sql: format!("NOT ({})", self.sql),
+            values: self.values,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LeftParen,
+    RightParen,
+    Comma,
+    Operator(Operator),
+    // bare word: field, keyword or unquoted value
+    Word(String),
+    Quoted(String),
+}
+
+impl Token {
+    fn text(&self) -> String {
+        match self {
+            Token::LeftParen => "(".to_string(),
+            Token::RightParen => ")".to_string(),
+            Token::Comma => ",".to_string(),
+            Token::Operator(operator) => operator.sql().to_string(),
+            Token::Word(word) | Token::Quoted(word) => word.clone(),
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Token::Word(word) if word.eq_ignore_ascii_case(keyword))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl Operator {
+    fn sql(&self) -> &'static str {
+        match self {
+            Operator::Equal => "=",
+            Operator::NotEqual => "!=",
+            Operator::Less => "<",
+            Operator::LessEqual => "<=",
+            Operator::Greater => ">",
+            Operator::GreaterEqual => ">=",
+        }
+    }
+}
+
+fn tokenize(expression: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | ',' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Token::LeftParen,
+                    ')' => Token::RightParen,
+                    _ => Token::Comma,
+                });
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let equals = chars.next_if_eq(&'=').is_some();
+                let operator = match (c, equals) {
+                    ('=', _) => Operator::Equal,
+                    ('!', true) => Operator::NotEqual,
+                    ('<', false) => Operator::Less,
+                    ('<', true) => Operator::LessEqual,
+                    ('>', false) => Operator::Greater,
+                    ('>', true) => Operator::GreaterEqual,
+                    _ => return Err(anyhow!("Expected != in filter")),
+                };
+                tokens.push(Token::Operator(operator));
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        Some(other) => text.push(other),
+                        None => return Err(anyhow!("Unterminated string in filter")),
+                    }
+                }
+                tokens.push(Token::Quoted(text));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() || matches!(c, '_' | '.' | ':' | '-' | '+') {
+                        word.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                if word.is_empty() {
+                    return Err(anyhow!("Unexpected character {:?} in filter", c));
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> anyhow::Result<Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| anyhow!("Unexpected end of filter"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn accept_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek().is_some_and(|token| token.is_keyword(keyword)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> anyhow::Result<()> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(anyhow!(
+                "Expected {:?} in filter, found {:?}",
+                expected.text(),
+                token.text()
+            ));
+        }
+        Ok(())
+    }
+
+    fn expression(&mut self) -> anyhow::Result<Condition> {
+        let mut condition = self.term()?;
+        while self.accept_keyword("or") {
+            condition = condition.or(self.term()?);
+        }
+        Ok(condition)
+    }
+
+    fn term(&mut self) -> anyhow::Result<Condition> {
+        let mut condition = self.factor()?;
+        while self.accept_keyword("and") {
+            condition = condition.and(self.factor()?);
+        }
+        Ok(condition)
+    }
+
+    fn factor(&mut self) -> anyhow::Result<Condition> {
+        if self.accept_keyword("not") {
+            return Ok(self.factor()?.not());
+        }
+        if self.peek() == Some(&Token::LeftParen) {
+            self.position += 1;
+            let condition = self.expression()?;
+            self.expect(Token::RightParen)?;
+            return Ok(condition);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> anyhow::Result<Condition> {
+        let field = match self.next()? {
+            Token::Word(word) => Field::parse(&word)?,
+            token => {
+                return Err(anyhow!(
+                    "Expected a field in filter, found {:?}",
+                    token.text()
+                ));
+            }
+        };
+
+        if self.accept_keyword("in") {
+            if self.peek() == Some(&Token::LeftParen) {
+                self.position += 1;
+                let mut values = vec![self.value()?];
+                while self.peek() == Some(&Token::Comma) {
+                    self.position += 1;
+                    values.push(self.value()?);
+                }
+                self.expect(Token::RightParen)?;
+                return compile_in(field, &values);
+            }
+            if field.kind() != FieldKind::Date {
+                return Err(anyhow!("{} in expects a list like (a, b)", field.name()));
+            }
+            return compile(field, Operator::Equal, &self.value()?);
+        }
+
+        let operator = match self.next()? {
+            Token::Operator(operator) => operator,
+            token => {
+                return Err(anyhow!(
+                    "Expected a comparison after {}, found {:?}",
+                    field.name(),
+                    token.text()
+                ));
+            }
+        };
+        compile(field, operator, &self.value()?)
+    }
+
+    fn value(&mut self) -> anyhow::Result<String> {
+        match self.next()? {
+            Token::Word(word) | Token::Quoted(word) => Ok(word),
+            token => Err(anyhow!(
+                "Expected a value in filter, found {:?}",
+                token.text()
+            )),
+        }
+    }
+}
+
+fn compile(field: Field, operator: Operator, value: &str) -> anyhow::Result<Condition> {
+    let column = field.column();
+    match field.kind() {
+        FieldKind::Date => {
+            let (start, end) = Period::parse(value)?.epoch_bounds();
+            let (sql, values) = match operator {
+                Operator::Equal => (format!("{column} >= ? AND {column} < ?"), vec![start, end]),
+                Operator::NotEqual => {
+                    (format!("({column} < ? OR {column} >= ?)"), vec![start, end])
+                }
+                Operator::Less => (format!("{column} < ?"), vec![start]),
+                Operator::LessEqual => (format!("{column} < ?"), vec![end]),
+                Operator::Greater => (format!("{column} >= ?"), vec![end]),
+                Operator::GreaterEqual => (format!("{column} >= ?"), vec![start]),
+            };
+            Ok(Condition::new(
+                sql,
+                values.into_iter().map(Value::Integer).collect(),
+            ))
+        }
+        FieldKind::Text => match operator {
+            Operator::Equal | Operator::NotEqual => Ok(Condition::new(
+                format!("{} {} ? COLLATE NOCASE", column, operator.sql()),
+                vec![parse_value(field, value)?],
+            )),
+            _ => Err(anyhow!(
+                "{} is text and only supports = and !=",
+                field.name()
+            )),
+        },
+        _ => Ok(Condition::new(
+            format!("{} {} ?", column, operator.sql()),
+            vec![parse_value(field, value)?],
+        )),
+    }
+}
+
+fn compile_in(field: Field, values: &[String]) -> anyhow::Result<Condition> {
+    if field.kind() == FieldKind::Date {
+        return Err(anyhow!("date in expects a period like 2026-Q2, not a list"));
+    }
+    let placeholders = vec!["?"; values.len()].join(", ");
+    let values = values
+        .iter()
+        .map(|value| parse_value(field, value))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    match field.kind() {
+        FieldKind::Text => Ok(Condition::new(
+            format!("{} COLLATE NOCASE IN ({})", field.column(), placeholders),
+            values,
+        )),
+        _ => Ok(Condition::new(
+            format!("{} IN ({})", field.column(), placeholders),
+            values,
+        )),
+    }
+}
+
+// value of a non-date field, in the unit the database stores it in
+fn parse_value(field: Field, value: &str) -> anyhow::Result<Value> {
+    let number = |text: &str| -> anyhow::Result<f64> {
+        text.parse::<f64>()
+            .with_context(|| format!("Invalid value {:?} for {}", value, field.name()))
+    };
+
+    match field.kind() {
+        FieldKind::Text if field == Field::Sport => Ok(Value::Text(sport_name(value))),
+        FieldKind::Text => Ok(Value::Text(value.to_string())),
+        FieldKind::Integer => Ok(Value::Integer(value.parse().with_context(|| {
+            format!("{} expects a whole number, got {:?}", field.name(), value)
+        })?)),
+        FieldKind::Number => Ok(Value::Real(number(value)?)),
+        FieldKind::Length => {
+            let (amount, unit) = split_unit(value);
+            let factor = match unit {
+                "m" => 1.0,
+                "km" => 1000.0,
+                "mi" => 1609.344,
+                _ => {
+                    return Err(anyhow!(
+                        "{} expects a length with unit m, km or mi, got {:?}",
+                        field.name(),
+                        value
+                    ));
+                }
+            };
+            Ok(Value::Real(number(amount)? * factor))
+        }
+        FieldKind::Duration => {
+            if value.contains(':') {
+                // h:mm:ss or mm:ss
+                let seconds = value.split(':').try_fold(0.0, |total, part| {
+                    Ok::<f64, anyhow::Error>(total * 60.0 + number(part)?)
+                })?;
+                return Ok(Value::Real(seconds));
+            }
+            let (amount, unit) = split_unit(value);
+            let factor = match unit {
+                "s" => 1.0,
+                "min" => 60.0,
+                "h" => 3600.0,
+                _ => {
+                    return Err(anyhow!(
+                        "{} expects a duration like 45min, 1.5h, 90s or 1:30:00, got {:?}",
+                        field.name(),
+                        value
+                    ));
+                }
+            };
+            Ok(Value::Real(number(amount)? * factor))
+        }
+        FieldKind::Date => Err(anyhow!("date values are compared as periods")),
+    }
+}
+
+// sport as stored from the .fit files for common short names, e.g. run -> running
+pub fn sport_name(name: &str) -> String {
+    let sport = match name.to_ascii_lowercase().as_str() {
+        "run" => "running",
+        "ride" | "bike" | "cycle" => "cycling",
+        "swim" => "swimming",
+        "walk" => "walking",
+        "hike" => "hiking",
+        "row" => "rowing",
+        "strength" | "gym" => "training",
+        _ => name,
+    };
+    sport.to_string()
+}
+
+fn split_unit(value: &str) -> (&str, &str) {
+    let index = value
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(value.len());
+    value.split_at(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn epoch(year: i32, month: u32, day: u32) -> Value {
+        Value::Integer(
+            NaiveDate::from_ymd_opt(year, month, day)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_utc()
+                .timestamp(),
+        )
+    }
+
+    fn parse(expression: &str) -> Condition {
+        Condition::parse(expression).unwrap()
+    }
+
+    fn error(expression: &str) -> String {
+        Condition::parse(expression).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        let condition = parse("  ");
+        assert_eq!(condition.sql, "1");
+        assert!(condition.values.is_empty());
+    }
+
+    #[test]
+    fn comparison_compiles_to_placeholder() {
+        let condition = parse("avg_hr < 150");
+        assert_eq!(condition.sql, "activities.avg_hr < ?");
+        assert_eq!(condition.values, vec![Value::Real(150.0)]);
+
+        let condition = parse("id = 7");
+        assert_eq!(condition.sql, "activities.id = ?");
+        assert_eq!(condition.values, vec![Value::Integer(7)]);
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let condition = parse("avg_hr > 1 or avg_hr > 2 and avg_hr > 3");
+        assert_eq!(
+            condition.sql,
+            "(activities.avg_hr > ?) OR ((activities.avg_hr > ?) AND (activities.avg_hr > ?))"
+        );
+        assert_eq!(
+            condition.values,
+            vec![Value::Real(1.0), Value::Real(2.0), Value::Real(3.0)]
+        );
+    }
+
+    #[test]
+    fn not_and_parentheses() {
+        let condition = parse("not (avg_hr > 1 or avg_hr > 2) and avg_hr > 3");
+        assert_eq!(
+            condition.sql,
+            "(NOT ((activities.avg_hr > ?) OR (activities.avg_hr > ?))) AND (activities.avg_hr > ?)"
+        );
+
+        let condition = parse("NOT not calories >= 500");
+        assert_eq!(condition.sql, "NOT (NOT (activities.calories >= ?))");
+    }
+
+    #[test]
+    fn text_is_bound_and_case_insensitive() {
+        let condition = parse("sport = \"O'Brien\" or workout != 'say \"hi\"'");
+        assert!(!condition.sql.contains('\''));
+        assert_eq!(
+            condition.values,
+            vec![
+                Value::Text("O'Brien".to_string()),
+                Value::Text("say \"hi\"".to_string())
+            ]
+        );
+        assert!(
+            condition
+                .sql
+                .contains("activities.workout != ? COLLATE NOCASE")
+        );
+
+        let condition = parse("sport in (Run, bike, rowing)");
+        assert_eq!(
+            condition.sql,
+            "activities.sport COLLATE NOCASE IN (?, ?, ?)"
+        );
+        assert_eq!(
+            condition.values,
+            vec![
+                Value::Text("running".to_string()),
+                Value::Text("cycling".to_string()),
+                Value::Text("rowing".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lengths_need_units() {
+        assert_eq!(parse("distance > 10km").values, vec![Value::Real(10_000.0)]);
+        assert_eq!(parse("distance > 800m").values, vec![Value::Real(800.0)]);
+        assert_eq!(parse("elevation > 1.5km").values, vec![Value::Real(1500.0)]);
+        assert_eq!(
+            parse("distance >= 26.2mi").values,
+            vec![Value::Real(26.2 * 1609.344)]
+        );
+        assert!(error("distance > 10").contains("unit m, km or mi"));
+        assert!(error("distance > 10ft").contains("\"10ft\""));
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse("duration > 45min").values, vec![Value::Real(2700.0)]);
+        assert_eq!(parse("duration > 1.5h").values, vec![Value::Real(5400.0)]);
+        assert_eq!(parse("duration > 90s").values, vec![Value::Real(90.0)]);
+        assert_eq!(
+            parse("duration < 1:30:00").values,
+            vec![Value::Real(5400.0)]
+        );
+        assert_eq!(parse("duration < 45:30").values, vec![Value::Real(2730.0)]);
+        assert!(error("duration > 45").contains("duration like"));
+        assert!(error("duration > 1:xx").contains("\"1:xx\""));
+    }
+
+    #[test]
+    fn date_periods() {
+        let column = Field::Date.column();
+        let condition = parse("date in 2026-Q2");
+        assert_eq!(condition.sql, format!("{column} >= ? AND {column} < ?"));
+        assert_eq!(condition.values, vec![epoch(2026, 4, 1), epoch(2026, 7, 1)]);
+
+        // ISO week 7 of 2026 starts on Monday February 9
+        assert_eq!(
+            parse("date = 2026-W07").values,
+            vec![epoch(2026, 2, 9), epoch(2026, 2, 16)]
+        );
+        assert_eq!(
+            parse("date in 2026-02").values,
+            vec![epoch(2026, 2, 1), epoch(2026, 3, 1)]
+        );
+        assert_eq!(
+            parse("date in 2025-12..2026-Q1").values,
+            vec![epoch(2025, 12, 1), epoch(2026, 4, 1)]
+        );
+
+        let condition = parse("date != 2026-05-14");
+        assert_eq!(condition.sql, format!("({column} < ? OR {column} >= ?)"));
+        assert_eq!(
+            condition.values,
+            vec![epoch(2026, 5, 14), epoch(2026, 5, 15)]
+        );
+    }
+
+    #[test]
+    fn date_ordering_uses_period_edges() {
+        assert_eq!(parse("date < 2026").values, vec![epoch(2026, 1, 1)]);
+        assert_eq!(parse("date <= 2026").values, vec![epoch(2027, 1, 1)]);
+        assert_eq!(parse("date > 2026").values, vec![epoch(2027, 1, 1)]);
+        assert_eq!(parse("date >= 2026").values, vec![epoch(2026, 1, 1)]);
+        assert!(parse("date <= 2026").sql.ends_with("< ?"));
+        assert!(parse("date > 2026").sql.ends_with(">= ?"));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(error("date in 2147483647").contains("out of range"));
+        assert!(error("date in 2026-Q5").contains("quarter"));
+        assert!(error("date in (2026, 2027)").contains("not a list"));
+        assert!(error("pace > 5").contains("Unknown field \"pace\""));
+        assert!(error("sport < running").contains("only supports = and !="));
+        assert!(error("avg_hr < 150 or").contains("end of filter"));
+        assert!(error("(avg_hr < 150").contains("end of filter"));
+        assert!(error("avg_hr < 150)").contains("Unexpected \")\""));
+        assert!(error("avg_hr ! 150").contains("!="));
+        assert!(error("id = 1.5").contains("whole number"));
+        assert!(error("avg_hr < fast").contains("\"fast\""));
+        assert!(error("sport = 'running").contains("Unterminated"));
+        assert!(error("avg_hr in 150").contains("list"));
+    }
+
+    #[test]
+    fn conditions_combine_with_their_values() {
+        let condition = Condition::period(&Period::Year(2026))
+            .and(Condition::any_of(Field::SubSport, &["Trail".to_string()]));
+        assert_eq!(
+            condition.values,
+            vec![
+                epoch(2026, 1, 1),
+                epoch(2027, 1, 1),
+                Value::Text("Trail".to_string())
+            ]
+        );
+        assert_eq!(condition.sql.matches('?').count(), condition.values.len());
+        assert_eq!(Condition::any_of(Field::Sport, &[]).sql, "1");
+    }
+}
@@ -0,0 +1,375 @@
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// time constants (days) of the acute and chronic exponentially weighted load averages
+const ATL_DAYS: f64 = 7.0;
+const CTL_DAYS: f64 = 42.0;
+
+// rolling windows (days) of the acute:chronic workload ratio
+const ACUTE_WINDOW: usize = 7;
+const CHRONIC_WINDOW: usize = 28;
+
+// ACWR outside this band is commonly associated with injury risk or detraining
+pub const ACWR_HIGH: f64 = 1.5;
+pub const ACWR_LOW: f64 = 0.8;
+
+// Banister TRIMP weighting; these are Banister's constants for men (0.86 and 1.67 for women),
+// used for every athlete since the profile has no sex
+const TRIMP_A: f64 = 0.64;
+const TRIMP_B: f64 = 1.92;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum LoadMethod {
+    // TSS if power is available, then TRIMP, then session-RPE
+    Auto,
+    // session RPE x duration in minutes
+    Srpe,
+    // Banister training impulse from heart rate reserve
+    Trimp,
+    // training stress score from power relative to FTP
+    Tss,
+}
+
+// athlete parameters the load methods depend on
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Thresholds {
+    pub max_hr: Option<f64>,
+    pub resting_hr: Option<f64>,
+    pub ftp: Option<f64>,
+}
+
+// activity fields used to compute its load
+#[derive(Debug, Clone)]
+pub struct LoadInput {
+    pub id: i64,
+    pub timestamp: DateTime<FixedOffset>,
+    pub sport: String,
+    // in seconds
+    pub duration: f64,
+    pub avg_hr: Option<f64>,
+    pub avg_power: Option<f64>,
+    pub rpe: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivityLoad {
+    pub id: i64,
+    pub timestamp: DateTime<FixedOffset>,
+    pub sport: String,
+    pub method: LoadMethod,
+    pub load: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyLoad {
+    pub date: NaiveDate,
+    pub load: f64,
+    // acute training load (fatigue)
+    pub atl: f64,
+    // chronic training load (fitness)
+    pub ctl: f64,
+    // training stress balance (form), yesterday's CTL - ATL
+    pub tsb: f64,
+    // CTL change over the last 7 days
+    pub ramp: f64,
+    // acute:chronic workload ratio, None until a full chronic window of data exists
+    pub acwr: Option<f64>,
+    // ACWR and ramp rate warnings, separated by "; "
+    pub warnings: Option<String>,
+}
+
+impl LoadMethod {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LoadMethod::Auto => "auto",
+            LoadMethod::Srpe => "session-RPE",
+            LoadMethod::Trimp => "TRIMP",
+            LoadMethod::Tss => "TSS",
+        }
+    }
+}
+
+impl LoadInput {
+    // load of the activity with the method that was used, None if the data is missing
+    pub fn load(&self, method: LoadMethod, thresholds: &Thresholds) -> Option<(LoadMethod, f64)> {
+        match method {
+            LoadMethod::Auto => self
+                .load(LoadMethod::Tss, thresholds)
+                .or_else(|| self.load(LoadMethod::Trimp, thresholds))
+                .or_else(|| self.load(LoadMethod::Srpe, thresholds)),
+            LoadMethod::Srpe => {
+                let rpe = self.rpe?;
+                Some((method, rpe * self.duration / 60.0))
+            }
+            LoadMethod::Trimp => {
+                let (avg_hr, max_hr, resting_hr) =
+                    (self.avg_hr?, thresholds.max_hr?, thresholds.resting_hr?);
+                if max_hr <= resting_hr {
+                    return None;
+                }
+                let reserve = ((avg_hr - resting_hr) / (max_hr - resting_hr)).clamp(0.0, 1.0);
+                let trimp = self.duration / 60.0 * reserve * TRIMP_A * (TRIMP_B * reserve).exp();
+                Some((method, trimp))
+            }
+            LoadMethod::Tss => {
+                // average power stands in for normalized power
+                let (avg_power, ftp) = (self.avg_power?, thresholds.ftp?);
+                if ftp <= 0.0 {
+                    return None;
+                }
+                let intensity = avg_power / ftp;
+                Some((
+                    method,
+                    self.duration / 3600.0 * intensity * intensity * 100.0,
+                ))
+            }
+        }
+    }
+}
+
+// per-day load with fitness, fatigue and form from the first activity until `until`
+pub fn daily_loads(activities: &[ActivityLoad], until: NaiveDate, max_ramp: f64) -> Vec<DailyLoad> {
+    let mut per_day: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+    for activity in activities {
+        *per_day
+            .entry(activity.timestamp.date_naive())
+            .or_insert(0.0) += activity.load;
+    }
+
+    let Some(first) = per_day.keys().next().copied() else {
+        return Vec::new();
+    };
+
+    let mut days: Vec<DailyLoad> = Vec::new();
+    let (mut atl, mut ctl) = (0.0, 0.0);
+    let mut date = first;
+    while date <= until {
+        let load = per_day.get(&date).copied().unwrap_or(0.0);
+        let tsb = ctl - atl;
+        atl += (load - atl) / ATL_DAYS;
+        ctl += (load - ctl) / CTL_DAYS;
+
+        let index = days.len();
+        let ramp = if index >= 7 {
+            ctl - days[index - 7].ctl
+        } else {
+            ctl
+        };
+        let acwr = acwr(&days, load);
+
+        let mut warnings = Vec::new();
+        if let Some(acwr) = acwr {
+            if acwr > ACWR_HIGH {
+                warnings.push(format!("ACWR {:.2} above {}", acwr, ACWR_HIGH));
+            } else if acwr < ACWR_LOW {
+                warnings.push(format!("ACWR {:.2} below {}", acwr, ACWR_LOW));
+            }
+        }
+        if index >= 7 && ramp > max_ramp {
+            warnings.push(format!("ramp rate {:.1} above {}", ramp, max_ramp));
+        }
+
+        days.push(DailyLoad {
+            date,
+            load,
+            atl,
+            ctl,
+            tsb,
+            ramp,
+            acwr,
+            warnings: (!warnings.is_empty()).then(|| warnings.join("; ")),
+        });
+        date += Duration::days(1);
+    }
+
+    days
+}
+
+// rolling acute average over rolling chronic average, including today's load
+fn acwr(previous: &[DailyLoad], today: f64) -> Option<f64> {
+    if previous.len() + 1 < CHRONIC_WINDOW {
+        return None;
+    }
+    let window_sum = |days: usize| {
+        today
+            + previous
+                .iter()
+                .rev()
+                .take(days - 1)
+                .map(|d| d.load)
+                .sum::<f64>()
+    };
+    let acute = window_sum(ACUTE_WINDOW) / ACUTE_WINDOW as f64;
+    let chronic = window_sum(CHRONIC_WINDOW) / CHRONIC_WINDOW as f64;
+    if chronic > 0.0 {
+        Some(acute / chronic)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn input(avg_hr: Option<f64>, avg_power: Option<f64>, rpe: Option<f64>) -> LoadInput {
+        LoadInput {
+            id: 1,
+            timestamp: FixedOffset::east_opt(0)
+                .unwrap()
+                .with_ymd_and_hms(2026, 3, 2, 8, 0, 0)
+                .unwrap(),
+            sport: "cycling".to_string(),
+            duration: 3600.0,
+            avg_hr,
+            avg_power,
+            rpe,
+        }
+    }
+
+    // one activity with `load` on each day from 2026-03-02, none for a load of 0
+    fn activities(loads: &[f64]) -> Vec<ActivityLoad> {
+        let start = FixedOffset::east_opt(3600)
+            .unwrap()
+            .with_ymd_and_hms(2026, 3, 2, 18, 0, 0)
+            .unwrap();
+        loads
+            .iter()
+            .enumerate()
+            .filter(|(_, load)| **load > 0.0)
+            .map(|(day, load)| ActivityLoad {
+                id: day as i64,
+                timestamp: start + Duration::days(day as i64),
+                sport: "running".to_string(),
+                method: LoadMethod::Srpe,
+                load: *load,
+            })
+            .collect()
+    }
+
+    fn date(day: i64) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, 2).unwrap() + Duration::days(day)
+    }
+
+    #[test]
+    fn load_of_each_method() {
+        let thresholds = Thresholds {
+            max_hr: Some(190.0),
+            resting_hr: Some(50.0),
+            ftp: Some(250.0),
+        };
+        let activity = input(Some(150.0), Some(200.0), Some(5.0));
+
+        let (method, srpe) = activity.load(LoadMethod::Srpe, &thresholds).unwrap();
+        assert_eq!(method, LoadMethod::Srpe);
+        assert_eq!(srpe, 300.0);
+        let (method, tss) = activity.load(LoadMethod::Tss, &thresholds).unwrap();
+        assert_eq!(method, LoadMethod::Tss);
+        assert!((tss - 64.0).abs() < 1e-9);
+        let (method, trimp) = activity.load(LoadMethod::Trimp, &thresholds).unwrap();
+        assert_eq!(method, LoadMethod::Trimp);
+        assert!((trimp - 108.095).abs() < 1e-3);
+
+        // auto takes the first method the activity and thresholds have data for
+        let auto = |activity: &LoadInput, thresholds: &Thresholds| {
+            activity
+                .load(LoadMethod::Auto, thresholds)
+                .map(|(method, _)| method)
+        };
+        assert_eq!(auto(&activity, &thresholds), Some(LoadMethod::Tss));
+        let no_ftp = Thresholds {
+            ftp: None,
+            ..thresholds
+        };
+        assert_eq!(auto(&activity, &no_ftp), Some(LoadMethod::Trimp));
+        assert_eq!(
+            auto(&input(None, Some(200.0), Some(5.0)), &no_ftp),
+            Some(LoadMethod::Srpe)
+        );
+        assert_eq!(auto(&input(None, None, None), &thresholds), None);
+
+        // thresholds that cannot give a load
+        let inverted = Thresholds {
+            max_hr: Some(50.0),
+            ..thresholds
+        };
+        assert_eq!(activity.load(LoadMethod::Trimp, &inverted), None);
+        let zero_ftp = Thresholds {
+            ftp: Some(0.0),
+            ..thresholds
+        };
+        assert_eq!(activity.load(LoadMethod::Tss, &zero_ftp), None);
+    }
+
+    #[test]
+    fn fatigue_fitness_and_form() {
+        let days = daily_loads(&activities(&[70.0, 0.0, 42.0]), date(3), 100.0);
+        assert_eq!(
+            days.iter().map(|day| day.date).collect::<Vec<_>>(),
+            (0..4).map(date).collect::<Vec<_>>()
+        );
+        assert_eq!(days[0].load, 70.0);
+        assert!((days[0].atl - 10.0).abs() < 1e-9);
+        assert!((days[0].ctl - 70.0 / 42.0).abs() < 1e-9);
+        // form is yesterday's fitness minus fatigue
+        assert_eq!(days[0].tsb, 0.0);
+        assert!((days[1].tsb - (days[0].ctl - days[0].atl)).abs() < 1e-9);
+        assert!((days[1].atl - 10.0 * 6.0 / 7.0).abs() < 1e-9);
+        assert!((days[2].atl - (days[1].atl + (42.0 - days[1].atl) / 7.0)).abs() < 1e-9);
+        // before a week of data the ramp is the fitness itself
+        assert_eq!(days[3].ramp, days[3].ctl);
+        assert!(
+            days.iter()
+                .all(|day| day.acwr.is_none() && day.warnings.is_none())
+        );
+
+        assert!(daily_loads(&[], date(3), 5.0).is_empty());
+    }
+
+    #[test]
+    fn ramp_rate_warning() {
+        let days = daily_loads(&activities(&[100.0; 10]), date(9), 5.0);
+        assert!((days[7].ramp - (days[7].ctl - days[0].ctl)).abs() < 1e-9);
+        assert!(days[6].warnings.is_none());
+        assert!(
+            days[7]
+                .warnings
+                .as_deref()
+                .is_some_and(|warnings| warnings.starts_with("ramp rate"))
+        );
+
+        let days = daily_loads(&activities(&[100.0; 10]), date(9), 100.0);
+        assert!(days.iter().all(|day| day.warnings.is_none()));
+    }
+
+    #[test]
+    fn acute_chronic_workload_ratio() {
+        // steady load: a ratio of 1 once the chronic window is full
+        let days = daily_loads(&activities(&[50.0; 28]), date(27), 100.0);
+        assert_eq!(days[26].acwr, None);
+        assert!((days[27].acwr.unwrap() - 1.0).abs() < 1e-9);
+
+        // a hard week after three easy ones
+        let mut loads = [1.0; 28];
+        loads[21..].fill(100.0);
+        let days = daily_loads(&activities(&loads), date(27), 1000.0);
+        let acwr = (700.0 / 7.0) / (721.0 / 28.0);
+        assert!((days[27].acwr.unwrap() - acwr).abs() < 1e-9);
+        assert_eq!(
+            days[27].warnings.as_deref(),
+            Some(format!("ACWR {:.2} above {}", acwr, ACWR_HIGH).as_str())
+        );
+
+        // a rest week after three loaded ones
+        let mut loads = [100.0; 28];
+        loads[21..].fill(0.0);
+        let days = daily_loads(&activities(&loads), date(27), 1000.0);
+        assert_eq!(days[27].acwr, Some(0.0));
+        assert_eq!(days[27].warnings.as_deref(), Some("ACWR 0.00 below 0.8"));
+    }
+}
@@ -1,8 +1,23 @@
+mod athlete;
+mod body;
 mod cli;
 mod commands;
 mod config;
 mod db;
+mod efforts;
+mod filter;
+mod load;
 mod models;
+mod output;
+mod period;
+mod rpe;
+mod streams;
+mod strength;
+mod summary;
+mod swim;
+mod timestamps;
+mod wellness;
+mod zones;
 
 use clap::Parser;
 use cli::Cli;
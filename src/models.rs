@@ -1,23 +1,29 @@
+use crate::wellness::WellnessSamples;
+use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Local, Utc};
 
 #[derive(Debug)]
 pub struct File {
     pub filename: String,
-}
-
-impl File {
-    pub fn new(filename: String) -> Self {
-        Self {
-            filename: filename
-        }
-    }
+    pub path: String,
+    // hex SHA-256 of the file contents
+    pub hash: String,
+    // in bytes
+    pub size: i64,
+    // modification time in unix seconds
+    pub mtime: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Activity {
     pub sport: String,
 
+    // e.g. trail, treadmill, indoor_cycling
+    pub sub_sport: Option<String>,
+
+    // name of the device's sport profile, e.g. "Trail Run"
+    pub profile: Option<String>,
+
     pub timestamp: DateTime<Local>,
 
     // in seconds
@@ -43,12 +49,17 @@ pub struct Activity {
 
     // was RPE estimated?
     pub rpe_est: bool,
+
+    // in m, only for pool swims
+    pub pool_length: Option<f64>,
 }
 
 impl Activity {
     pub fn new() -> Self {
         Self {
             sport: "Unknown".to_owned(),
+            sub_sport: None,
+            profile: None,
             timestamp: Local::now(),
             duration: 0.0,
             distance: None,
@@ -58,6 +69,7 @@ impl Activity {
             avg_power: None,
             rpe: None,
             rpe_est: false,
+            pool_length: None,
         }
     }
 
@@ -92,3 +104,205 @@ impl Device {
         self.product == "Unknown"
     }
 }
+
+// one FIT record message, the per-second samples of an activity
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub timestamp: DateTime<Local>,
+
+    // in bpm
+    pub heart_rate: Option<f64>,
+
+    // in W
+    pub power: Option<f64>,
+
+    // in m/s
+    pub speed: Option<f64>,
+
+    // in rpm
+    pub cadence: Option<f64>,
+
+    // in m
+    pub altitude: Option<f64>,
+
+    // in degrees
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+
+    // in m
+    pub distance: Option<f64>,
+}
+
+impl Record {
+    pub fn new(timestamp: DateTime<Local>) -> Self {
+        Self {
+            timestamp,
+            heart_rate: None,
+            power: None,
+            speed: None,
+            cadence: None,
+            altitude: None,
+            lat: None,
+            lon: None,
+            distance: None,
+        }
+    }
+}
+
+// one FIT lap message
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Lap {
+    pub start_time: DateTime<Local>,
+
+    // in seconds
+    pub duration: f64,
+
+    // in meters
+    pub distance: Option<f64>,
+
+    // in bpm
+    pub avg_hr: Option<f64>,
+    pub max_hr: Option<f64>,
+
+    // in W
+    pub avg_power: Option<f64>,
+
+    // in rpm (strides per minute for running)
+    pub avg_cadence: Option<f64>,
+
+    // what ended the lap: manual, time, distance, position_start, session_end, ...
+    pub trigger: Option<String>,
+}
+
+impl Lap {
+    pub fn new(start_time: DateTime<Local>) -> Self {
+        Self {
+            start_time,
+            duration: 0.0,
+            distance: None,
+            avg_hr: None,
+            max_hr: None,
+            avg_power: None,
+            avg_cadence: None,
+            trigger: None,
+        }
+    }
+}
+
+// one active FIT set message of a strength activity
+#[derive(Debug, Clone)]
+pub struct Set {
+    pub start_time: DateTime<Local>,
+
+    // in seconds
+    pub duration: Option<f64>,
+
+    pub repetitions: Option<i64>,
+
+    // in kg, None for bodyweight exercises
+    pub weight: Option<f64>,
+
+    // FIT exercise category, e.g. bench_press, squat
+    pub exercise: Option<String>,
+}
+
+impl Set {
+    pub fn new(start_time: DateTime<Local>) -> Self {
+        Self {
+            start_time,
+            duration: None,
+            repetitions: None,
+            weight: None,
+            exercise: None,
+        }
+    }
+}
+
+// one FIT length message of a pool swim
+#[derive(Debug, Clone)]
+pub struct Length {
+    pub start_time: DateTime<Local>,
+
+    // in seconds
+    pub duration: Option<f64>,
+
+    pub strokes: Option<i64>,
+
+    // freestyle, backstroke, breaststroke, butterfly, drill, ...
+    pub stroke: Option<String>,
+
+    // idle lengths are rest intervals
+    pub active: bool,
+}
+
+impl Length {
+    pub fn new(start_time: DateTime<Local>) -> Self {
+        Self {
+            start_time,
+            duration: None,
+            strokes: None,
+            stroke: None,
+            active: true,
+        }
+    }
+}
+
+// one FIT weight scale message
+#[derive(Debug, Clone)]
+pub struct BodyMetrics {
+    pub timestamp: DateTime<Local>,
+
+    // user profile of multi-user scales
+    pub user_index: Option<i64>,
+
+    // in kg
+    pub weight: Option<f64>,
+
+    // in %
+    pub body_fat: Option<f64>,
+
+    // in kg
+    pub muscle_mass: Option<f64>,
+    pub bone_mass: Option<f64>,
+
+    // in %
+    pub hydration: Option<f64>,
+
+    pub bmi: Option<f64>,
+}
+
+impl BodyMetrics {
+    pub fn new(timestamp: DateTime<Local>) -> Self {
+        Self {
+            timestamp,
+            user_index: None,
+            weight: None,
+            body_fat: None,
+            muscle_mass: None,
+            bone_mass: None,
+            hydration: None,
+            bmi: None,
+        }
+    }
+}
+
+// everything read from one .fit file
+#[derive(Debug, Default)]
+pub struct FitData {
+    pub sessions: Vec<Activity>,
+    pub devices: Vec<Device>,
+    pub laps: Vec<Lap>,
+    pub sets: Vec<Set>,
+    pub lengths: Vec<Length>,
+    pub body_metrics: Vec<BodyMetrics>,
+    // only decoded when record import is enabled
+    pub records: Vec<Record>,
+    // monitoring, sleep, stress and HRV samples
+    pub wellness: WellnessSamples,
+    // UTC offset in seconds where the file was recorded, from its activity or monitoring info message
+    pub utc_offset: Option<i32>,
+    // name of the planned workout that was followed
+    pub workout: Option<String>,
+    // sport profile name from the file's sport message, for sessions without one
+    pub profile: Option<String>,
+}
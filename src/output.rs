@@ -0,0 +1,71 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::{self, Write};
+
+// how command results are written to stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    // human readable text
+    #[default]
+    Table,
+    // a single pretty-printed JSON document
+    Json,
+    // comma separated rows with a header line
+    Csv,
+    // one JSON object per line
+    Ndjson,
+}
+
+impl OutputFormat {
+    // print flat rows; json emits them as an array, table output is left to the command
+    pub fn print_rows<R: Serialize>(
+        self,
+        rows: &[R],
+        table: impl FnOnce() -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        match self {
+            OutputFormat::Table => table(),
+            OutputFormat::Json => Self::write_json(&rows),
+            OutputFormat::Csv => Self::write_csv(rows),
+            OutputFormat::Ndjson => Self::write_ndjson(rows),
+        }
+    }
+
+    // print a nested document as json, falling back to its flat rows for csv and ndjson
+    pub fn print_document<D: Serialize, R: Serialize>(
+        self,
+        document: &D,
+        rows: &[R],
+        table: impl FnOnce() -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        match self {
+            OutputFormat::Json => Self::write_json(document),
+            _ => self.print_rows(rows, table),
+        }
+    }
+
+    fn write_json<T: Serialize + ?Sized>(value: &T) -> anyhow::Result<()> {
+        let mut stdout = io::stdout().lock();
+        serde_json::to_writer_pretty(&mut stdout, value)?;
+        writeln!(stdout)?;
+        Ok(())
+    }
+
+    fn write_ndjson<R: Serialize>(rows: &[R]) -> anyhow::Result<()> {
+        let mut stdout = io::stdout().lock();
+        for row in rows {
+            serde_json::to_writer(&mut stdout, row)?;
+            writeln!(stdout)?;
+        }
+        Ok(())
+    }
+
+    fn write_csv<R: Serialize>(rows: &[R]) -> anyhow::Result<()> {
+        let mut writer = csv::Writer::from_writer(io::stdout().lock());
+        for row in rows {
+            writer.serialize(row)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,612 @@
+use anyhow::{Context, anyhow};
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Weekday,
+};
+
+// accepted by every --period option, the summary period subcommand and filter dates
+pub const PERIOD_HELP: &str = "Nd, week, month or year (the current one), YYYY, YYYY-Qn, YYYY-MM, YYYY-Wnn, YYYY-MM-DD or FROM..TO";
+
+// time period used to select activities, either rolling or calendar-based
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    // rolling window ending now
+    LastDays(u16),
+    // rolling window of `days` days ending `ago` days before now
+    DaysAgo { days: u16, ago: u32 },
+    // ISO week
+    Week { year: i32, week: u32 },
+    Month { year: i32, month: u32 },
+    Quarter { year: i32, quarter: u32 },
+    Year(i32),
+    // both dates inclusive
+    Range { from: NaiveDate, to: NaiveDate },
+}
+
+impl Period {
+    pub fn current_week() -> Self {
+        let iso = Local::now().date_naive().iso_week();
+        Period::Week {
+            year: iso.year(),
+            week: iso.week(),
+        }
+    }
+
+    pub fn current_month() -> Self {
+        let today = Local::now().date_naive();
+        Period::Month {
+            year: today.year(),
+            month: today.month(),
+        }
+    }
+
+    pub fn current_year() -> Self {
+        Period::Year(Local::now().year())
+    }
+
+    // parse "WEEKNUM-YEAR", e.g. "12-2026", or "2026-W12"
+    pub fn parse_week(value: &str) -> anyhow::Result<Self> {
+        if let Ok(week @ Period::Week { .. }) = Self::parse_calendar(value) {
+            return Ok(week);
+        }
+        let (week, year) = Self::split_num_year(value)?;
+        if NaiveDate::from_isoywd_opt(year, week, Weekday::Mon).is_none() {
+            return Err(anyhow!("{} has no ISO week {}", year, week));
+        }
+        Ok(Period::Week { year, week })
+    }
+
+    // parse "MONTHNUM-YEAR", e.g. "3-2026", or "2026-03"
+    pub fn parse_month(value: &str) -> anyhow::Result<Self> {
+        if let Ok(month @ Period::Month { .. }) = Self::parse_calendar(value) {
+            return Ok(month);
+        }
+        let (month, year) = Self::split_num_year(value)?;
+        if !(1..=12).contains(&month) {
+            return Err(anyhow!("Invalid month {}", month));
+        }
+        Ok(Period::Month { year, month })
+    }
+
+    pub fn parse_year(value: &str) -> anyhow::Result<Self> {
+        let year: i32 = value
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid year {:?}", value))?;
+        Ok(Period::Year(Self::check_year(year)?))
+    }
+
+    // years chrono can represent up to the start of the following year
+    fn check_year(year: i32) -> anyhow::Result<i32> {
+        let (min, max) = (NaiveDate::MIN.year(), NaiveDate::MAX.year() - 1);
+        if !(min..=max).contains(&year) {
+            return Err(anyhow!(
+                "Year {} is out of range, expected {} to {}",
+                year,
+                min,
+                max
+            ));
+        }
+        Ok(year)
+    }
+
+    pub fn range(from: NaiveDate, to: NaiveDate) -> anyhow::Result<Self> {
+        if to < from {
+            return Err(anyhow!("Range end {} is before start {}", to, from));
+        }
+        Ok(Period::Range { from, to })
+    }
+
+    // parse a period spec: "Nd", "week", "month" or "year" for the current one, a calendar
+    // period (see parse_calendar) or "FROM..[TO]" from the start of one calendar period to the
+    // end of another, TO defaulting to today
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let value = value.trim();
+        if let Some((from, to)) = value.split_once("..") {
+            let (from, _) = Self::parse_calendar(from)?.date_bounds();
+            let to = if to.is_empty() {
+                Local::now().date_naive()
+            } else {
+                let (_, end) = Self::parse_calendar(to)?.date_bounds();
+                end.pred_opt().unwrap_or(end)
+            };
+            return Self::range(from, to);
+        }
+
+        match value {
+            "week" => Ok(Self::current_week()),
+            "month" => Ok(Self::current_month()),
+            "year" => Ok(Self::current_year()),
+            _ => match value.strip_suffix('d').map(str::parse::<u16>) {
+                Some(Ok(days)) if days > 0 => Ok(Period::LastDays(days)),
+                _ => Self::parse_calendar(value),
+            },
+        }
+    }
+
+    // parse a calendar period: "YYYY", "YYYY-Qn", "YYYY-MM", "YYYY-Wnn" (ISO week) or
+    // "YYYY-MM-DD"
+    fn parse_calendar(value: &str) -> anyhow::Result<Self> {
+        let value = value.trim();
+        let invalid = || anyhow!("Invalid period {:?}, expected {}", value, PERIOD_HELP);
+
+        if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+            return Self::range(date, date);
+        }
+
+        // a leading minus belongs to the year
+        let (year, rest) = match value.get(1..).and_then(|tail| tail.find('-')) {
+            Some(index) => (&value[..=index], Some(&value[index + 2..])),
+            None => (value, None),
+        };
+        let year = Self::check_year(year.parse().map_err(|_| invalid())?)?;
+        let number = |digits: &str| digits.parse::<u32>().map_err(|_| invalid());
+
+        match rest {
+            None => Ok(Period::Year(year)),
+            Some(rest) if rest.starts_with(['Q', 'q']) => {
+                let quarter = number(&rest[1..])?;
+                if !(1..=4).contains(&quarter) {
+                    return Err(anyhow!("Invalid quarter {}", quarter));
+                }
+                Ok(Period::Quarter { year, quarter })
+            }
+            Some(rest) if rest.starts_with(['W', 'w']) => {
+                let week = number(&rest[1..])?;
+                if NaiveDate::from_isoywd_opt(year, week, Weekday::Mon).is_none() {
+                    return Err(anyhow!("{} has no ISO week {}", year, week));
+                }
+                Ok(Period::Week { year, week })
+            }
+            Some(rest) => {
+                let month = number(rest)?;
+                if !(1..=12).contains(&month) {
+                    return Err(anyhow!("Invalid month {}", month));
+                }
+                Ok(Period::Month { year, month })
+            }
+        }
+    }
+
+    fn split_num_year(value: &str) -> anyhow::Result<(u32, i32)> {
+        let (num, year) = value
+            .trim()
+            .split_once('-')
+            .ok_or_else(|| anyhow!("Expected NUM-YEAR, got {:?}", value))?;
+        let num: u32 = num
+            .parse()
+            .with_context(|| format!("Invalid number in {:?}", value))?;
+        let year: i32 = year
+            .parse()
+            .with_context(|| format!("Invalid year in {:?}", value))?;
+        Ok((num, Self::check_year(year)?))
+    }
+
+    // period of the same kind and length right before this one
+    pub fn previous(&self) -> anyhow::Result<Self> {
+        let out_of_range = || anyhow!("The period before {} is out of range", self.label());
+        let period = match *self {
+            Period::LastDays(days) => Period::DaysAgo {
+                days,
+                ago: days.into(),
+            },
+            Period::DaysAgo { days, ago } => Period::DaysAgo {
+                days,
+                ago: ago.checked_add(days.into()).ok_or_else(out_of_range)?,
+            },
+            Period::Week { year, week } => {
+                let iso = NaiveDate::from_isoywd_opt(year, week, Weekday::Mon)
+                    .and_then(|start| start.checked_sub_signed(Duration::weeks(1)))
+                    .ok_or_else(out_of_range)?
+                    .iso_week();
+                Period::Week {
+                    year: iso.year(),
+                    week: iso.week(),
+                }
+            }
+            Period::Month { year, month: 1 } => Period::Month {
+                year: Self::check_year(year - 1)?,
+                month: 12,
+            },
+            Period::Month { year, month } => Period::Month {
+                year,
+                month: month - 1,
+            },
+            Period::Quarter { year, quarter: 1 } => Period::Quarter {
+                year: Self::check_year(year - 1)?,
+                quarter: 4,
+            },
+            Period::Quarter { year, quarter } => Period::Quarter {
+                year,
+                quarter: quarter - 1,
+            },
+            Period::Year(year) => Period::Year(Self::check_year(year - 1)?),
+            Period::Range { from, to } => {
+                let days = (to - from).num_days() + 1;
+                Period::Range {
+                    from: from
+                        .checked_sub_signed(Duration::days(days))
+                        .ok_or_else(out_of_range)?,
+                    to: from.pred_opt().ok_or_else(out_of_range)?,
+                }
+            }
+        };
+        Ok(period)
+    }
+
+    // the same period one year earlier; week 53 falls back to week 52 and February 29 to the 28th
+    pub fn year_before(&self) -> anyhow::Result<Self> {
+        let out_of_range = || anyhow!("The year before {} is out of range", self.label());
+        let shift = |date: NaiveDate| {
+            date.with_year(date.year() - 1)
+                .or_else(|| date.pred_opt()?.with_year(date.year() - 1))
+                .ok_or_else(out_of_range)
+        };
+
+        let period = match *self {
+            Period::LastDays(days) => Period::DaysAgo { days, ago: 365 },
+            Period::DaysAgo { days, ago } => Period::DaysAgo {
+                days,
+                ago: ago.checked_add(365).ok_or_else(out_of_range)?,
+            },
+            Period::Week { year, week } => {
+                let year = Self::check_year(year - 1)?;
+                let week = [week, 52]
+                    .into_iter()
+                    .find(|week| NaiveDate::from_isoywd_opt(year, *week, Weekday::Mon).is_some())
+                    .ok_or_else(out_of_range)?;
+                Period::Week { year, week }
+            }
+            Period::Month { year, month } => Period::Month {
+                year: Self::check_year(year - 1)?,
+                month,
+            },
+            Period::Quarter { year, quarter } => Period::Quarter {
+                year: Self::check_year(year - 1)?,
+                quarter,
+            },
+            Period::Year(year) => Period::Year(Self::check_year(year - 1)?),
+            Period::Range { from, to } => Period::Range {
+                from: shift(from)?,
+                to: shift(to)?,
+            },
+        };
+        Ok(period)
+    }
+
+    // start (inclusive) and end (exclusive) of the period in local time
+    pub fn bounds(&self) -> (NaiveDateTime, NaiveDateTime) {
+        match *self {
+            Period::LastDays(days) => {
+                let now = Local::now().naive_local();
+                (now - Duration::days(days.into()), now)
+            }
+            Period::DaysAgo { days, ago } => {
+                let end = Local::now().naive_local() - Duration::days(ago.into());
+                (end - Duration::days(days.into()), end)
+            }
+            Period::Week { year, week } => {
+                let start = NaiveDate::from_isoywd_opt(year, week, Weekday::Mon)
+                    .unwrap_or_else(|| Self::first_day(year, 1));
+                let end = start
+                    .checked_add_signed(Duration::weeks(1))
+                    .unwrap_or(NaiveDate::MAX);
+                (Self::midnight(start), Self::midnight(end))
+            }
+            Period::Month { year, month } => {
+                let end = if month == 12 {
+                    Self::first_day(year.saturating_add(1), 1)
+                } else {
+                    Self::first_day(year, month + 1)
+                };
+                (
+                    Self::midnight(Self::first_day(year, month)),
+                    Self::midnight(end),
+                )
+            }
+            Period::Quarter { year, quarter } => {
+                let end = if quarter == 4 {
+                    Self::first_day(year.saturating_add(1), 1)
+                } else {
+                    Self::first_day(year, quarter * 3 + 1)
+                };
+                (
+                    Self::midnight(Self::first_day(year, quarter * 3 - 2)),
+                    Self::midnight(end),
+                )
+            }
+            Period::Year(year) => (
+                Self::midnight(Self::first_day(year, 1)),
+                Self::midnight(Self::first_day(year.saturating_add(1), 1)),
+            ),
+            Period::Range { from, to } => (
+                Self::midnight(from),
+                Self::midnight(to.succ_opt().unwrap_or(NaiveDate::MAX)),
+            ),
+        }
+    }
+
+    // period bounds resolved to the local timezone, for comparing against stored timestamps
+    pub fn local_bounds(&self) -> (DateTime<Local>, DateTime<Local>) {
+        let (start, end) = self.bounds();
+        (Self::to_local(start), Self::to_local(end))
+    }
+
+    // period bounds as wall-clock epoch seconds, compared against an activity's local time
+    // (its UTC timestamp plus its UTC offset) so activities count by the time they were recorded at
+    pub fn epoch_bounds(&self) -> (i64, i64) {
+        let (start, end) = self.bounds();
+        (start.and_utc().timestamp(), end.and_utc().timestamp())
+    }
+
+    // whole days of the period, from the first day (inclusive) to the last day (exclusive);
+    // a rolling period starts with the first full day
+    pub fn date_bounds(&self) -> (NaiveDate, NaiveDate) {
+        let (start, end) = self.bounds();
+        let whole_day = |time: NaiveDateTime| {
+            if time.time() == NaiveTime::MIN {
+                time.date()
+            } else {
+                time.date() + Duration::days(1)
+            }
+        };
+        (whole_day(start), whole_day(end))
+    }
+
+    fn to_local(naive: NaiveDateTime) -> DateTime<Local> {
+        // earliest() picks the first of two ambiguous (DST fold) times; a time skipped by a DST gap
+        // is treated as UTC, which is close enough for period boundaries
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .unwrap_or_else(|| Local.from_utc_datetime(&naive))
+    }
+
+    fn midnight(date: NaiveDate) -> NaiveDateTime {
+        date.and_time(NaiveTime::MIN)
+    }
+
+    // first day of a month, clamped to the dates chrono can represent; the parsers reject
+    // years outside that range
+    fn first_day(year: i32, month: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, 1).unwrap_or(if year < 0 {
+            NaiveDate::MIN
+        } else {
+            NaiveDate::MAX
+        })
+    }
+
+    pub fn label(&self) -> String {
+        match *self {
+            Period::LastDays(days) => format!("{}-Day", days),
+            Period::DaysAgo { days, ago } => format!("{}-Day ending {} days ago", days, ago),
+            Period::Week { year, week } => format!("Week {}-{}", week, year),
+            Period::Month { .. } => {
+                let (start, _) = self.bounds();
+                start.format("%B %Y").to_string()
+            }
+            Period::Quarter { year, quarter } => format!("Q{} {}", quarter, year),
+            Period::Year(year) => year.to_string(),
+            Period::Range { from, to } if from == to => from.to_string(),
+            Period::Range { from, to } => format!("{} to {}", from, to),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn calendar_bounds() {
+        let bounds = |period: Period| {
+            let (start, end) = period.bounds();
+            (start.date(), end.date())
+        };
+        assert_eq!(
+            bounds(Period::parse_week("1-2026").unwrap()),
+            (date(2025, 12, 29), date(2026, 1, 5))
+        );
+        assert_eq!(
+            bounds(Period::parse_month("12-2025").unwrap()),
+            (date(2025, 12, 1), date(2026, 1, 1))
+        );
+        assert_eq!(
+            bounds(Period::parse_year("2024").unwrap()),
+            (date(2024, 1, 1), date(2025, 1, 1))
+        );
+        assert_eq!(
+            bounds(Period::range(date(2026, 3, 1), date(2026, 3, 31)).unwrap()),
+            (date(2026, 3, 1), date(2026, 4, 1))
+        );
+    }
+
+    #[test]
+    fn parse_period_specs() {
+        assert_eq!(Period::parse("90d").unwrap(), Period::LastDays(90));
+        assert_eq!(Period::parse("2026").unwrap(), Period::Year(2026));
+        assert_eq!(
+            Period::parse("2026-Q2").unwrap(),
+            Period::Quarter {
+                year: 2026,
+                quarter: 2
+            }
+        );
+        assert_eq!(
+            Period::parse("2026-03").unwrap(),
+            Period::Month {
+                year: 2026,
+                month: 3
+            }
+        );
+        assert_eq!(
+            Period::parse("2026-W07").unwrap(),
+            Period::Week {
+                year: 2026,
+                week: 7
+            }
+        );
+        assert_eq!(
+            Period::parse("2026-03-15").unwrap(),
+            Period::Range {
+                from: date(2026, 3, 15),
+                to: date(2026, 3, 15)
+            }
+        );
+        // a range runs from the start of FROM to the end of TO
+        assert_eq!(
+            Period::parse("2026-03..2026-Q3").unwrap(),
+            Period::Range {
+                from: date(2026, 3, 1),
+                to: date(2026, 9, 30)
+            }
+        );
+        assert_eq!(
+            Period::parse("2026-W01..2026-W01").unwrap(),
+            Period::Range {
+                from: date(2025, 12, 29),
+                to: date(2026, 1, 4)
+            }
+        );
+        // summary week and month also take their legacy NUM-YEAR spelling
+        assert_eq!(
+            Period::parse_week("7-2026").unwrap(),
+            Period::parse("2026-W07").unwrap()
+        );
+        assert_eq!(
+            Period::parse_month("3-2026").unwrap(),
+            Period::parse("2026-03").unwrap()
+        );
+
+        for invalid in [
+            "0d",
+            "abc",
+            "2026-Q5",
+            "2026-13",
+            "2025-W53",
+            "2026-06..2026-Q1",
+            "90d..2026",
+        ] {
+            assert!(Period::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn rejects_years_out_of_range() {
+        assert!(Period::parse_year("999999").is_err());
+        assert!(Period::parse_month("3-999999").is_err());
+        assert!(Period::parse_week("3-999999").is_err());
+        assert!(Period::parse("999999-03").is_err());
+        assert!(Period::parse("2147483647").is_err());
+        assert!(Period::parse_month("13-2026").is_err());
+        assert!(Period::parse_week("53-2025").is_err());
+    }
+
+    #[test]
+    fn bounds_at_the_edges_of_the_date_range() {
+        let max = NaiveDate::MAX.year() - 1;
+        let min = NaiveDate::MIN.year();
+        Period::parse_year(&max.to_string()).unwrap().bounds();
+        Period::parse_year(&min.to_string()).unwrap().bounds();
+        Period::parse_month(&format!("12-{}", max))
+            .unwrap()
+            .bounds();
+        Period::range(date(2020, 1, 1), NaiveDate::MAX)
+            .unwrap()
+            .bounds();
+    }
+
+    #[test]
+    fn previous_and_year_before() {
+        assert_eq!(
+            Period::parse_week("1-2026").unwrap().previous().unwrap(),
+            Period::Week {
+                year: 2025,
+                week: 52
+            }
+        );
+        assert_eq!(
+            Period::parse_month("1-2026").unwrap().previous().unwrap(),
+            Period::Month {
+                year: 2025,
+                month: 12
+            }
+        );
+        assert_eq!(
+            Period::range(date(2026, 3, 1), date(2026, 3, 10))
+                .unwrap()
+                .previous()
+                .unwrap(),
+            Period::Range {
+                from: date(2026, 2, 19),
+                to: date(2026, 2, 28)
+            }
+        );
+        assert_eq!(
+            Period::parse_week("53-2026")
+                .unwrap()
+                .year_before()
+                .unwrap(),
+            Period::Week {
+                year: 2025,
+                week: 52
+            }
+        );
+        assert_eq!(
+            Period::range(date(2024, 2, 29), date(2024, 3, 5))
+                .unwrap()
+                .year_before()
+                .unwrap(),
+            Period::Range {
+                from: date(2023, 2, 28),
+                to: date(2023, 3, 5)
+            }
+        );
+        assert_eq!(
+            Period::parse("2026-Q1").unwrap().previous().unwrap(),
+            Period::Quarter {
+                year: 2025,
+                quarter: 4
+            }
+        );
+        assert_eq!(
+            Period::LastDays(7).previous().unwrap(),
+            Period::DaysAgo { days: 7, ago: 7 }
+        );
+    }
+
+    #[test]
+    fn comparisons_at_the_edges_of_the_date_range() {
+        let min = NaiveDate::MIN.year();
+        for spec in [
+            format!("{}", min),
+            format!("{}-01", min),
+            format!("{}-Q1", min),
+            format!("{}-01-01..", min),
+        ] {
+            let period = Period::parse(&spec).unwrap();
+            assert!(period.previous().is_err(), "{}", spec);
+            assert!(period.year_before().is_err(), "{}", spec);
+        }
+
+        // the first week with a Monday chrono can represent
+        let monday = NaiveDate::MIN
+            .iter_days()
+            .find(|date| date.weekday() == Weekday::Mon)
+            .unwrap()
+            .iso_week();
+        let week = Period::Week {
+            year: monday.year(),
+            week: monday.week(),
+        };
+        assert!(week.previous().is_err());
+        assert!(week.year_before().is_err());
+
+        let max = NaiveDate::MAX.year() - 1;
+        let last = Period::parse(&format!("{}", max)).unwrap();
+        assert_eq!(last.previous().unwrap(), Period::Year(max - 1));
+    }
+}
@@ -0,0 +1,368 @@
+use crate::config::Config;
+use anyhow::{Context, anyhow};
+use chrono::{DateTime, Local};
+use linfa::dataset::DatasetBase;
+use linfa::prelude::Fit;
+use linfa_linear::LinearRegression;
+use ndarray::{Array1, Array2};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+// minimum number of workouts with a logged RPE before a sport gets a model
+pub const MIN_SAMPLES: usize = 10;
+
+// share of training workouts that must record a feature for it to be used
+const MIN_FEATURE_COVERAGE: f64 = 0.8;
+
+const CV_FOLDS: usize = 5;
+
+static MODELS_FILENAME: &str = "rpe_models.toml";
+
+// candidate model inputs, all read from the activities table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    AvgHr,
+    AvgPower,
+    Elevation,
+    Duration,
+    Distance,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 5] = [
+        Feature::AvgHr,
+        Feature::AvgPower,
+        Feature::Elevation,
+        Feature::Duration,
+        Feature::Distance,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Feature::AvgHr => "avg_hr",
+            Feature::AvgPower => "avg_power",
+            Feature::Elevation => "elevation",
+            Feature::Duration => "duration",
+            Feature::Distance => "distance",
+        }
+    }
+}
+
+// one workout with every candidate feature, None where the workout did not record it
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub id: i64,
+    pub sport: String,
+    pub avg_hr: Option<f64>,
+    pub avg_power: Option<f64>,
+    pub elevation: Option<f64>,
+    pub duration: Option<f64>,
+    pub distance: Option<f64>,
+    pub rpe: Option<f64>,
+    // was RPE estimated?
+    pub rpe_est: bool,
+}
+
+impl Sample {
+    pub fn feature(&self, feature: Feature) -> Option<f64> {
+        match feature {
+            Feature::AvgHr => self.avg_hr,
+            Feature::AvgPower => self.avg_power,
+            Feature::Elevation => self.elevation,
+            Feature::Duration => self.duration,
+            Feature::Distance => self.distance,
+        }
+    }
+
+    fn features(&self, features: &[Feature]) -> Option<Vec<f64>> {
+        features.iter().map(|f| self.feature(*f)).collect()
+    }
+}
+
+// linear RPE model for one sport, including the min/max scaling of its training data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpeModel {
+    pub sport: String,
+    pub features: Vec<Feature>,
+    // (min, max) of each feature in the training data
+    pub scaling: Vec<(f64, f64)>,
+    pub coefficients: Vec<f64>,
+    pub intercept: f64,
+    pub samples: usize,
+    // cross-validated mean absolute error and coefficient of determination
+    pub mae: Option<f64>,
+    pub r2: Option<f64>,
+    pub trained_at: DateTime<Local>,
+}
+
+impl RpeModel {
+    // train a model on the sport's workouts with a logged RPE; None if there is too little data
+    pub fn train(sport: &str, samples: &[Sample]) -> anyhow::Result<Option<Self>> {
+        let samples: Vec<&Sample> = samples
+            .iter()
+            .filter(|s| s.sport == sport && s.rpe.is_some() && !s.rpe_est)
+            .collect();
+        let features = Self::select_features(&samples);
+        if features.is_empty() {
+            return Ok(None);
+        }
+
+        let (rows, targets): (Vec<Vec<f64>>, Vec<f64>) = samples
+            .iter()
+            .filter_map(|s| Some((s.features(&features)?, s.rpe?)))
+            .unzip();
+        if rows.len() < MIN_SAMPLES.max(features.len() + 2) {
+            return Ok(None);
+        }
+
+        let (scaling, coefficients, intercept) = Self::fit(&rows, &targets)
+            .with_context(|| format!("Failed to train RPE model for {}", sport))?;
+        let (mae, r2) = Self::cross_validate(&rows, &targets);
+
+        Ok(Some(Self {
+            sport: sport.to_string(),
+            features,
+            scaling,
+            coefficients,
+            intercept,
+            samples: rows.len(),
+            mae,
+            r2,
+            trained_at: Local::now(),
+        }))
+    }
+
+    // estimated RPE on the 1-10 scale, None if the workout lacks one of the model's features
+    pub fn predict(&self, sample: &Sample) -> Option<f64> {
+        let row = sample.features(&self.features)?;
+        Some(Self::predict_row(
+            &row,
+            &self.scaling,
+            &self.coefficients,
+            self.intercept,
+        ))
+    }
+
+    fn predict_row(
+        row: &[f64],
+        scaling: &[(f64, f64)],
+        coefficients: &[f64],
+        intercept: f64,
+    ) -> f64 {
+        let estimate = Self::scale_row(row, scaling)
+            .iter()
+            .zip(coefficients)
+            .fold(intercept, |acc, (value, coefficient)| {
+                acc + value * coefficient
+            });
+
+        // RPE is only defined on a 1-10 scale
+        estimate.clamp(1.0, 10.0)
+    }
+
+    // use features recorded by most workouts of the sport that actually vary between workouts
+    fn select_features(samples: &[&Sample]) -> Vec<Feature> {
+        Feature::ALL
+            .into_iter()
+            .filter(|feature| {
+                let values: Vec<f64> = samples.iter().filter_map(|s| s.feature(*feature)).collect();
+                let coverage = values.len() as f64 / samples.len().max(1) as f64;
+                let varies = values.windows(2).any(|pair| pair[0] != pair[1]);
+                coverage >= MIN_FEATURE_COVERAGE && varies
+            })
+            .collect()
+    }
+
+    fn min_max(rows: &[Vec<f64>]) -> Vec<(f64, f64)> {
+        let num_features = rows[0].len();
+        let mut min_max = vec![(f64::INFINITY, f64::NEG_INFINITY); num_features];
+
+        for row in rows {
+            for (i, &val) in row.iter().enumerate() {
+                min_max[i].0 = min_max[i].0.min(val);
+                min_max[i].1 = min_max[i].1.max(val);
+            }
+        }
+
+        min_max
+    }
+
+    fn scale_row(row: &[f64], scaling: &[(f64, f64)]) -> Vec<f64> {
+        row.iter()
+            .zip(scaling)
+            .map(|(&val, &(min_val, max_val))| {
+                if max_val > min_val {
+                    (val - min_val) / (max_val - min_val)
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    // returns (scaling, coefficients, intercept)
+    #[allow(clippy::type_complexity)]
+    fn fit(rows: &[Vec<f64>], targets: &[f64]) -> anyhow::Result<(Vec<(f64, f64)>, Vec<f64>, f64)> {
+        let scaling = Self::min_max(rows);
+        let scaled: Vec<f64> = rows
+            .iter()
+            .flat_map(|row| Self::scale_row(row, &scaling))
+            .collect();
+
+        let features_array = Array2::from_shape_vec((rows.len(), scaling.len()), scaled)?;
+        let targets_array = Array1::from_vec(targets.to_vec());
+        let dataset = DatasetBase::new(features_array, targets_array);
+
+        let model = LinearRegression::new()
+            .fit(&dataset)
+            .map_err(|err| anyhow!("{}", err))?;
+
+        Ok((scaling, model.params().to_vec(), model.intercept()))
+    }
+
+    // k-fold cross-validation over out-of-fold predictions, None if a fold cannot be trained
+    fn cross_validate(rows: &[Vec<f64>], targets: &[f64]) -> (Option<f64>, Option<f64>) {
+        let folds = CV_FOLDS.min(rows.len());
+        let mut errors = Vec::with_capacity(rows.len());
+
+        for fold in 0..folds {
+            let (train_rows, train_targets): (Vec<Vec<f64>>, Vec<f64>) = rows
+                .iter()
+                .zip(targets)
+                .enumerate()
+                .filter(|(i, _)| i % folds != fold)
+                .map(|(_, (row, target))| (row.clone(), *target))
+                .unzip();
+
+            let Ok((scaling, coefficients, intercept)) = Self::fit(&train_rows, &train_targets)
+            else {
+                return (None, None);
+            };
+
+            for (i, (row, target)) in rows.iter().zip(targets).enumerate() {
+                if i % folds == fold {
+                    let predicted = Self::predict_row(row, &scaling, &coefficients, intercept);
+                    errors.push((predicted, *target));
+                }
+            }
+        }
+
+        let n = errors.len() as f64;
+        let mae = errors.iter().map(|(p, t)| (p - t).abs()).sum::<f64>() / n;
+        let mean = targets.iter().sum::<f64>() / targets.len() as f64;
+        let ss_res: f64 = errors.iter().map(|(p, t)| (t - p).powi(2)).sum();
+        let ss_tot: f64 = targets.iter().map(|t| (t - mean).powi(2)).sum();
+        let r2 = if ss_tot > 0.0 {
+            Some(1.0 - ss_res / ss_tot)
+        } else {
+            None
+        };
+
+        (Some(mae), r2)
+    }
+}
+
+// all trained models, stored next to the database so estimates stay stable between runs
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RpeModels {
+    #[serde(default)]
+    pub models: Vec<RpeModel>,
+}
+
+impl RpeModels {
+    pub fn path(config: &Config) -> PathBuf {
+        config.get_data_path().join(MODELS_FILENAME)
+    }
+
+    // None if no models have been trained yet
+    pub fn load(config: &Config) -> anyhow::Result<Option<Self>> {
+        let path = Self::path(config);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(&path).context("Failed to read RPE model file")?;
+        let models = toml::from_str(&contents).context("Failed to parse RPE model file")?;
+
+        Ok(Some(models))
+    }
+
+    pub fn save(&self, config: &Config) -> anyhow::Result<()> {
+        let contents =
+            toml::to_string_pretty(self).context("Failed to serialize RPE models to toml")?;
+        fs::write(Self::path(config), contents).context("Failed to write RPE model file")?;
+
+        Ok(())
+    }
+
+    pub fn get(&self, sport: &str) -> Option<&RpeModel> {
+        self.models.iter().find(|model| model.sport == sport)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(avg_hr: f64, duration: f64) -> Sample {
+        Sample {
+            id: 0,
+            sport: "cycling".to_string(),
+            avg_hr: Some(avg_hr),
+            avg_power: None,
+            elevation: None,
+            duration: Some(duration),
+            distance: None,
+            // exactly linear in heart rate and duration
+            rpe: Some(1.0 + (avg_hr - 100.0) / 10.0 + (duration - 1800.0) / 1800.0),
+            rpe_est: false,
+        }
+    }
+
+    #[test]
+    fn saved_model_predicts_after_loading() {
+        let samples: Vec<Sample> = (0..20)
+            .map(|i| sample(100.0 + i as f64 * 3.0, 1800.0 + (i * 7 % 11) as f64 * 300.0))
+            .collect();
+        assert!(
+            RpeModel::train("cycling", &samples[..MIN_SAMPLES - 1])
+                .unwrap()
+                .is_none()
+        );
+
+        let model = RpeModel::train("cycling", &samples).unwrap().unwrap();
+        assert_eq!(model.features, vec![Feature::AvgHr, Feature::Duration]);
+        assert_eq!(model.scaling, vec![(100.0, 157.0), (1800.0, 4800.0)]);
+        assert_eq!(model.samples, 20);
+        // every fold of an exactly linear relation predicts the held out workouts
+        assert!(model.mae.unwrap() < 1e-6);
+        assert!((model.r2.unwrap() - 1.0).abs() < 1e-6);
+
+        let dir = std::env::temp_dir().join(format!("queryfit-{}-rpe", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut config = Config::default();
+        config.set_data_path(dir.clone());
+        RpeModels {
+            models: vec![model],
+        }
+        .save(&config)
+        .unwrap();
+        let loaded = RpeModels::load(&config).unwrap().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let model = loaded.get("cycling").unwrap();
+        assert!(loaded.get("running").is_none());
+        assert_eq!(model.scaling, vec![(100.0, 157.0), (1800.0, 4800.0)]);
+        let predicted = model.predict(&sample(130.0, 3600.0)).unwrap();
+        assert!((predicted - 5.0).abs() < 1e-6);
+        // outside the training range the estimate stays on the RPE scale
+        assert_eq!(model.predict(&sample(250.0, 3600.0)), Some(10.0));
+        let without_hr = Sample {
+            avg_hr: None,
+            ..sample(130.0, 3600.0)
+        };
+        assert_eq!(model.predict(&without_hr), None);
+    }
+}
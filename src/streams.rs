@@ -0,0 +1,143 @@
+use crate::models::Record;
+use chrono::{DateTime, Local};
+use rusqlite::{Connection, OptionalExtension, params};
+
+// longest gap between two records that still counts as moving time, in seconds
+pub const MAX_SAMPLE_GAP: f32 = 10.0;
+
+// per-record time series of one activity, stored column-wise as little-endian blobs;
+// samples without a value are NaN and channels without any value are None
+#[derive(Debug, Clone, Default)]
+pub struct Stream {
+    // seconds since the activity start
+    pub time: Vec<f32>,
+    // in bpm
+    pub heart_rate: Option<Vec<f32>>,
+    // in W
+    pub power: Option<Vec<f32>>,
+    // in m/s
+    pub speed: Option<Vec<f32>>,
+    // in rpm
+    pub cadence: Option<Vec<f32>>,
+    // in m
+    pub altitude: Option<Vec<f32>>,
+    // in degrees, kept as f64 so positions stay accurate to the centimeter
+    pub lat: Option<Vec<f64>>,
+    pub lon: Option<Vec<f64>>,
+    // in m
+    pub distance: Option<Vec<f32>>,
+}
+
+impl Stream {
+    // build the stream of an activity starting at `start`; None if there are no records
+    pub fn from_records(start: DateTime<Local>, records: &[&Record]) -> Option<Self> {
+        if records.is_empty() {
+            return None;
+        }
+
+        let channel = |value: fn(&Record) -> Option<f64>| -> Option<Vec<f32>> {
+            let values: Vec<f32> = records
+                .iter()
+                .map(|r| value(r).map_or(f32::NAN, |v| v as f32))
+                .collect();
+            values.iter().any(|v| !v.is_nan()).then_some(values)
+        };
+        let position = |value: fn(&Record) -> Option<f64>| -> Option<Vec<f64>> {
+            let values: Vec<f64> = records
+                .iter()
+                .map(|r| value(r).unwrap_or(f64::NAN))
+                .collect();
+            values.iter().any(|v| !v.is_nan()).then_some(values)
+        };
+
+        Some(Self {
+            time: records
+                .iter()
+                .map(|r| (r.timestamp - start).num_milliseconds() as f32 / 1000.0)
+                .collect(),
+            heart_rate: channel(|r| r.heart_rate),
+            power: channel(|r| r.power),
+            speed: channel(|r| r.speed),
+            cadence: channel(|r| r.cadence),
+            altitude: channel(|r| r.altitude),
+            lat: position(|r| r.lat),
+            lon: position(|r| r.lon),
+            distance: channel(|r| r.distance),
+        })
+    }
+
+    pub fn save(&self, conn: &Connection, activity_id: i64) -> anyhow::Result<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO streams (activity_id, samples, time, heart_rate, power, speed, cadence, altitude, lat, lon, distance) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                activity_id,
+                self.time.len() as i64,
+                encode_f32(&self.time),
+                self.heart_rate.as_deref().map(encode_f32),
+                self.power.as_deref().map(encode_f32),
+                self.speed.as_deref().map(encode_f32),
+                self.cadence.as_deref().map(encode_f32),
+                self.altitude.as_deref().map(encode_f32),
+                self.lat.as_deref().map(encode_f64),
+                self.lon.as_deref().map(encode_f64),
+                self.distance.as_deref().map(encode_f32),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    // None if the activity has no stored stream
+    pub fn load(conn: &Connection, activity_id: i64) -> anyhow::Result<Option<Self>> {
+        let stream = conn
+            .query_row(
+                "SELECT time, heart_rate, power, speed, cadence, altitude, lat, lon, distance FROM streams WHERE activity_id = ?1",
+                params![activity_id],
+                |row| {
+                    let f32_channel = |index: usize| -> rusqlite::Result<Option<Vec<f32>>> {
+                        Ok(row.get::<_, Option<Vec<u8>>>(index)?.map(|b| decode_f32(&b)))
+                    };
+                    let f64_channel = |index: usize| -> rusqlite::Result<Option<Vec<f64>>> {
+                        Ok(row.get::<_, Option<Vec<u8>>>(index)?.map(|b| decode_f64(&b)))
+                    };
+
+                    Ok(Self {
+                        time: decode_f32(&row.get::<_, Vec<u8>>(0)?),
+                        heart_rate: f32_channel(1)?,
+                        power: f32_channel(2)?,
+                        speed: f32_channel(3)?,
+                        cadence: f32_channel(4)?,
+                        altitude: f32_channel(5)?,
+                        lat: f64_channel(6)?,
+                        lon: f64_channel(7)?,
+                        distance: f32_channel(8)?,
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(stream)
+    }
+}
+
+fn encode_f32(values: &[f32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn encode_f64(values: &[f64]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn decode_f32(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes(chunk.try_into().expect("chunk of 4 bytes")))
+        .collect()
+}
+
+fn decode_f64(bytes: &[u8]) -> Vec<f64> {
+    bytes
+        .chunks_exact(8)
+        .map(|chunk| f64::from_le_bytes(chunk.try_into().expect("chunk of 8 bytes")))
+        .collect()
+}
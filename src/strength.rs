@@ -0,0 +1,170 @@
+use chrono::{DateTime, Datelike, FixedOffset};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// the Epley formula overestimates the 1RM of sets with more repetitions
+pub const MAX_1RM_REPS: i64 = 12;
+
+// one imported set with the start time of its activity
+#[derive(Debug, Clone)]
+pub struct StrengthSet {
+    pub activity_id: i64,
+    pub timestamp: DateTime<FixedOffset>,
+    pub exercise: String,
+    pub repetitions: Option<i64>,
+    // in kg
+    pub weight: Option<f64>,
+}
+
+impl StrengthSet {
+    // in kg, bodyweight sets count no volume
+    pub fn volume(&self) -> f64 {
+        match (self.repetitions, self.weight) {
+            (Some(reps), Some(weight)) => reps as f64 * weight,
+            _ => 0.0,
+        }
+    }
+
+    // Epley estimate of the one repetition maximum, in kg
+    pub fn estimated_1rm(&self) -> Option<f64> {
+        let reps = self
+            .repetitions
+            .filter(|r| (1..=MAX_1RM_REPS).contains(r))?;
+        let weight = self.weight?;
+        if reps == 1 {
+            Some(weight)
+        } else {
+            Some(weight * (1.0 + reps as f64 / 30.0))
+        }
+    }
+}
+
+// main muscle group trained by a FIT exercise category
+pub fn muscle_group(exercise: &str) -> &'static str {
+    match exercise {
+        "bench_press" | "flye" | "push_up" => "chest",
+        "row" | "pull_up" | "hyperextension" | "shrug" => "back",
+        "shoulder_press" | "lateral_raise" | "shoulder_stability" => "shoulders",
+        "curl" | "triceps_extension" => "arms",
+        "squat" | "deadlift" | "lunge" | "leg_curl" | "calf_raise" | "hip_raise"
+        | "hip_stability" | "hip_swing" | "sled" => "legs",
+        "core" | "crunch" | "plank" | "sit_up" | "leg_raise" | "chop" => "core",
+        "olympic_lift" | "total_body" | "carry" | "battle_rope" | "sandbag" | "plyo"
+        | "sledge_hammer" | "tire" | "suspension" | "banded_exercises" => "full body",
+        _ => "other",
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExerciseVolume {
+    pub exercise: String,
+    pub muscle_group: String,
+    pub sets: i64,
+    pub repetitions: i64,
+    // in kg
+    pub volume: f64,
+    // best estimated 1RM in kg
+    pub best_1rm: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MuscleGroupVolume {
+    pub muscle_group: String,
+    pub sets: i64,
+    pub repetitions: i64,
+    // in kg
+    pub volume: f64,
+}
+
+// best estimated 1RM of an exercise in one ISO week
+#[derive(Debug, Clone, Serialize)]
+pub struct OneRepMax {
+    pub exercise: String,
+    // ISO week, e.g. 2026-W07
+    pub week: String,
+    pub activity_id: i64,
+    pub timestamp: DateTime<FixedOffset>,
+    // in kg
+    pub estimate: f64,
+    pub weight: f64,
+    pub repetitions: i64,
+}
+
+// totals per exercise, largest volume first
+pub fn exercise_volumes(sets: &[StrengthSet]) -> Vec<ExerciseVolume> {
+    let mut by_exercise: BTreeMap<&str, ExerciseVolume> = BTreeMap::new();
+    for set in sets {
+        let entry = by_exercise
+            .entry(&set.exercise)
+            .or_insert_with(|| ExerciseVolume {
+                exercise: set.exercise.clone(),
+                muscle_group: muscle_group(&set.exercise).to_string(),
+                sets: 0,
+                repetitions: 0,
+                volume: 0.0,
+                best_1rm: None,
+            });
+        entry.sets += 1;
+        entry.repetitions += set.repetitions.unwrap_or(0);
+        entry.volume += set.volume();
+        if let Some(estimate) = set.estimated_1rm() {
+            entry.best_1rm = Some(entry.best_1rm.map_or(estimate, |best| best.max(estimate)));
+        }
+    }
+
+    let mut volumes: Vec<ExerciseVolume> = by_exercise.into_values().collect();
+    volumes.sort_by(|a, b| b.volume.total_cmp(&a.volume).then(b.sets.cmp(&a.sets)));
+    volumes
+}
+
+// totals per muscle group, largest volume first
+pub fn muscle_group_volumes(exercises: &[ExerciseVolume]) -> Vec<MuscleGroupVolume> {
+    let mut by_group: BTreeMap<&str, MuscleGroupVolume> = BTreeMap::new();
+    for exercise in exercises {
+        let entry = by_group
+            .entry(&exercise.muscle_group)
+            .or_insert_with(|| MuscleGroupVolume {
+                muscle_group: exercise.muscle_group.clone(),
+                sets: 0,
+                repetitions: 0,
+                volume: 0.0,
+            });
+        entry.sets += exercise.sets;
+        entry.repetitions += exercise.repetitions;
+        entry.volume += exercise.volume;
+    }
+
+    let mut volumes: Vec<MuscleGroupVolume> = by_group.into_values().collect();
+    volumes.sort_by(|a, b| b.volume.total_cmp(&a.volume).then(b.sets.cmp(&a.sets)));
+    volumes
+}
+
+// best estimated 1RM per exercise and week, by exercise then week
+pub fn progression(sets: &[StrengthSet]) -> Vec<OneRepMax> {
+    let mut best: BTreeMap<(&str, String), OneRepMax> = BTreeMap::new();
+    for set in sets {
+        let Some(estimate) = set.estimated_1rm() else {
+            continue;
+        };
+        let iso = set.timestamp.iso_week();
+        let week = format!("{}-W{:02}", iso.year(), iso.week());
+        let entry = OneRepMax {
+            exercise: set.exercise.clone(),
+            week: week.clone(),
+            activity_id: set.activity_id,
+            timestamp: set.timestamp,
+            estimate,
+            weight: set.weight.unwrap_or(0.0),
+            repetitions: set.repetitions.unwrap_or(0),
+        };
+        best.entry((&set.exercise, week))
+            .and_modify(|current| {
+                if estimate > current.estimate {
+                    *current = entry.clone();
+                }
+            })
+            .or_insert(entry);
+    }
+
+    best.into_values().collect()
+}
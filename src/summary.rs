@@ -0,0 +1,601 @@
+use crate::db::Database;
+use crate::filter::{Condition, Field};
+use crate::period::Period;
+use crate::swim::{Swim, SwimSummary};
+use crate::timestamps;
+use crate::zones::ZoneKind;
+use chrono::{DateTime, FixedOffset, Local};
+use clap::ValueEnum;
+use rusqlite::params_from_iter;
+use serde::Serialize;
+use std::collections::HashMap;
+
+// metrics the summary engine can aggregate, each backed by one activities column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    // total seconds
+    Duration,
+    // total meters
+    Distance,
+    // average kcal per activity
+    Calories,
+}
+
+enum Aggregation {
+    Total,
+    Average,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 3] = [Metric::Duration, Metric::Distance, Metric::Calories];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Metric::Duration => "Total Duration",
+            Metric::Distance => "Total Distance",
+            Metric::Calories => "Average Calories",
+        }
+    }
+
+    fn column_expr(&self) -> &'static str {
+        match self {
+            Metric::Duration => "duration",
+            Metric::Distance => "distance",
+            Metric::Calories => "calories",
+        }
+    }
+
+    fn aggregation(&self) -> Aggregation {
+        match self {
+            Metric::Duration | Metric::Distance => Aggregation::Total,
+            Metric::Calories => Aggregation::Average,
+        }
+    }
+}
+
+// aggregated values of one group of activities; metrics that were not requested and averages
+// without any values stay None
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Totals {
+    pub count: i64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distance: Option<f64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calories: Option<f64>,
+}
+
+impl Totals {
+    pub fn get(&self, metric: Metric) -> Option<f64> {
+        match metric {
+            Metric::Duration => self.duration,
+            Metric::Distance => self.distance,
+            Metric::Calories => self.calories,
+        }
+    }
+
+    fn set(&mut self, metric: Metric, value: f64) {
+        let slot = match metric {
+            Metric::Duration => &mut self.duration,
+            Metric::Distance => &mut self.distance,
+            Metric::Calories => &mut self.calories,
+        };
+        *slot = Some(value);
+    }
+}
+
+// period a summary is compared against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Comparison {
+    // the period of the same length right before
+    Previous,
+    // the same period one year earlier
+    LastYear,
+}
+
+impl Comparison {
+    pub fn period(&self, period: &Period) -> anyhow::Result<Period> {
+        match self {
+            Comparison::Previous => period.previous(),
+            Comparison::LastYear => period.year_before(),
+        }
+    }
+}
+
+// change of one metric against the comparison period
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricDelta {
+    pub metric: Metric,
+    // None for an average without any values
+    pub current: Option<f64>,
+    pub previous: Option<f64>,
+    // None unless both periods have a value
+    pub change: Option<f64>,
+    // None also when the comparison period's value is zero
+    pub percent: Option<f64>,
+}
+
+impl MetricDelta {
+    fn new(metric: Metric, current: Option<f64>, previous: Option<f64>) -> Self {
+        let change = current
+            .zip(previous)
+            .map(|(current, previous)| current - previous);
+        let percent = change
+            .zip(previous)
+            .filter(|(_, previous)| *previous != 0.0)
+            .map(|(change, previous)| change / previous.abs() * 100.0);
+        MetricDelta {
+            metric,
+            current,
+            previous,
+            change,
+            percent,
+        }
+    }
+}
+
+// totals of the comparison period and the change of every requested metric
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonSummary {
+    pub comparison: Comparison,
+    pub period: String,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub total: Totals,
+    pub deltas: Vec<MetricDelta>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SportSummary {
+    pub sport: String,
+
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivityEntry {
+    pub timestamp: DateTime<FixedOffset>,
+    pub sport: String,
+    pub sub_sport: Option<String>,
+    pub workout: Option<String>,
+    pub duration: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ZoneTime {
+    pub zone: i64,
+    pub seconds: f64,
+    pub percent: f64,
+}
+
+// time in zones of one kind over all activities of the period
+#[derive(Debug, Clone, Serialize)]
+pub struct ZoneSummary {
+    pub kind: ZoneKind,
+    // in seconds
+    pub total: f64,
+    pub zones: Vec<ZoneTime>,
+}
+
+impl ZoneSummary {
+    // percent of time in zones 1-2 and in zone 3 and above
+    pub fn polarization(&self) -> (f64, f64) {
+        let low: f64 = self
+            .zones
+            .iter()
+            .filter(|z| z.zone <= 2)
+            .map(|z| z.percent)
+            .sum();
+        (low, 100.0 - low)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    pub period: String,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub sports: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_sports: Option<Vec<String>>,
+    pub metrics: Vec<Metric>,
+    pub total: Totals,
+
+    // per-sport totals, most frequent sport first
+    pub by_sport: Vec<SportSummary>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activities: Option<Vec<ActivityEntry>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zones: Option<Vec<ZoneSummary>>,
+
+    // swim totals when the period has swimming activities
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub swim: Option<SwimSummary>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comparison: Option<ComparisonSummary>,
+}
+
+// flat per-sport row for tabular output formats
+#[derive(Debug, Clone, Serialize)]
+pub struct SummaryRow {
+    pub period: String,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    // None for the row totalling all sports
+    pub sport: Option<String>,
+    pub count: i64,
+    pub duration: Option<f64>,
+    pub distance: Option<f64>,
+    pub calories: Option<f64>,
+}
+
+impl Summary {
+    // total row first, then one row per sport and the total of the comparison period
+    pub fn rows(&self) -> Vec<SummaryRow> {
+        let current = (&self.period, self.start, self.end);
+        std::iter::once((current, None, &self.total))
+            .chain(
+                self.by_sport
+                    .iter()
+                    .map(|sport| (current, Some(sport.sport.clone()), &sport.totals)),
+            )
+            .chain(self.comparison.iter().map(|comparison| {
+                (
+                    (&comparison.period, comparison.start, comparison.end),
+                    None,
+                    &comparison.total,
+                )
+            }))
+            .map(|((period, start, end), sport, totals)| SummaryRow {
+                period: period.clone(),
+                start,
+                end,
+                sport,
+                count: totals.count,
+                duration: totals.duration,
+                distance: totals.distance,
+                calories: totals.calories,
+            })
+            .collect()
+    }
+}
+
+// what to summarize: a period, optional sport and sub-sport filters and the metrics to compute
+pub struct SummaryRequest<'a> {
+    pub period: Period,
+    pub sports: Option<&'a [String]>,
+    pub sub_sports: Option<&'a [String]>,
+    pub metrics: &'a [Metric],
+    pub list_activities: bool,
+    pub zones: bool,
+    pub compare: Option<Comparison>,
+}
+
+// running SUM/COUNT per metric, combined into totals or averages at the end
+#[derive(Default)]
+struct Accumulator {
+    count: i64,
+    sums: HashMap<Metric, (f64, i64)>,
+}
+
+impl Accumulator {
+    fn add(&mut self, metric: Metric, sum: Option<f64>, count: i64) {
+        let entry = self.sums.entry(metric).or_insert((0.0, 0));
+        entry.0 += sum.unwrap_or(0.0);
+        entry.1 += count;
+    }
+
+    fn merge(&mut self, other: &Accumulator) {
+        self.count += other.count;
+        for (metric, (sum, count)) in &other.sums {
+            self.add(*metric, Some(*sum), *count);
+        }
+    }
+
+    fn totals(&self, metrics: &[Metric]) -> Totals {
+        let mut totals = Totals {
+            count: self.count,
+            ..Totals::default()
+        };
+        for metric in metrics {
+            let (sum, count) = self.sums.get(metric).copied().unwrap_or((0.0, 0));
+            let value = match metric.aggregation() {
+                Aggregation::Total => Some(sum),
+                Aggregation::Average if count > 0 => Some(sum / count as f64),
+                Aggregation::Average => None,
+            };
+            if let Some(value) = value {
+                totals.set(*metric, value);
+            }
+        }
+        totals
+    }
+}
+
+impl SummaryRequest<'_> {
+    pub fn run(&self, db: &Database) -> anyhow::Result<Summary> {
+        let (start, end) = self.period.local_bounds();
+        let condition = self.condition();
+
+        // one grouped query computes SUM and COUNT of every requested metric per sport
+        let aggregates = self
+            .metrics
+            .iter()
+            .map(|metric| format!("SUM({0}), COUNT({0})", metric.column_expr()))
+            .collect::<Vec<_>>();
+        let query = format!(
+            "SELECT sport, COUNT(*){}{} FROM activities WHERE {} GROUP BY sport",
+            if aggregates.is_empty() { "" } else { ", " },
+            aggregates.join(", "),
+            condition.sql
+        );
+
+        let mut stmt = db.connection().prepare(&query)?;
+        let groups = stmt
+            .query_map(params_from_iter(&condition.values), |row| {
+                let sport: String = row.get(0)?;
+                let mut acc = Accumulator {
+                    count: row.get(1)?,
+                    ..Accumulator::default()
+                };
+                for (i, metric) in self.metrics.iter().enumerate() {
+                    acc.add(*metric, row.get(2 + 2 * i)?, row.get(3 + 2 * i)?);
+                }
+                Ok((sport, acc))
+            })?
+            .collect::<rusqlite::Result<Vec<(String, Accumulator)>>>()?;
+
+        let mut overall = Accumulator::default();
+        let mut by_sport: Vec<SportSummary> = groups
+            .iter()
+            .map(|(sport, acc)| {
+                overall.merge(acc);
+                SportSummary {
+                    sport: sport.clone(),
+                    totals: acc.totals(self.metrics),
+                }
+            })
+            .collect();
+        by_sport.sort_by(|a, b| {
+            b.totals
+                .count
+                .cmp(&a.totals.count)
+                .then_with(|| a.sport.cmp(&b.sport))
+        });
+
+        let activities = if self.list_activities {
+            Some(Self::activities(db, &condition)?)
+        } else {
+            None
+        };
+
+        let swims = Swim::load(db.connection(), &condition.sql, &condition.values)?;
+        let swim = SwimSummary::compute(&swims);
+
+        let zones = if self.zones {
+            Some(Self::zones(db, &condition)?)
+        } else {
+            None
+        };
+
+        let comparison = match self.compare {
+            Some(comparison) => Some(self.compare_with(db, comparison, &overall)?),
+            None => None,
+        };
+
+        Ok(Summary {
+            period: self.period.label(),
+            start,
+            end,
+            sports: self.sports.map(|sports| sports.to_vec()),
+            sub_sports: self.sub_sports.map(|sub_sports| sub_sports.to_vec()),
+            metrics: self.metrics.to_vec(),
+            total: overall.totals(self.metrics),
+            by_sport,
+            activities,
+            zones,
+            swim,
+            comparison,
+        })
+    }
+
+    // totals of the comparison period with the same filters and metrics
+    fn compare_with(
+        &self,
+        db: &Database,
+        comparison: Comparison,
+        overall: &Accumulator,
+    ) -> anyhow::Result<ComparisonSummary> {
+        let period = comparison.period(&self.period)?;
+        let previous = SummaryRequest {
+            period,
+            list_activities: false,
+            zones: false,
+            compare: None,
+            ..*self
+        }
+        .run(db)?;
+
+        let current = overall.totals(self.metrics);
+        let deltas = self
+            .metrics
+            .iter()
+            .map(|metric| {
+                MetricDelta::new(*metric, current.get(*metric), previous.total.get(*metric))
+            })
+            .collect();
+
+        Ok(ComparisonSummary {
+            comparison,
+            period: previous.period,
+            start: previous.start,
+            end: previous.end,
+            total: previous.total,
+            deltas,
+        })
+    }
+
+    // every section of the summary is filtered by the same bound condition
+    fn condition(&self) -> Condition {
+        let mut condition = Condition::period(&self.period);
+        for (field, values) in [
+            (Field::Sport, self.sports),
+            (Field::SubSport, self.sub_sports),
+        ] {
+            if let Some(values) = values {
+                condition = condition.and(Condition::any_of(field, values));
+            }
+        }
+        condition
+    }
+
+    fn activities(db: &Database, condition: &Condition) -> anyhow::Result<Vec<ActivityEntry>> {
+        let query = format!(
+            "SELECT timestamp, utc_offset, sport, sub_sport, workout, duration FROM activities WHERE {} ORDER BY timestamp DESC",
+            condition.sql
+        );
+
+        let mut stmt = db.connection().prepare(&query)?;
+
+        let activities = stmt
+            .query_map(params_from_iter(&condition.values), |row| {
+                Ok(ActivityEntry {
+                    timestamp: timestamps::activity_time(row, 0, 1)?,
+                    sport: row.get(2)?,
+                    sub_sport: row.get(3)?,
+                    workout: row.get(4)?,
+                    duration: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<ActivityEntry>, _>>()?;
+
+        Ok(activities)
+    }
+
+    fn zones(db: &Database, condition: &Condition) -> anyhow::Result<Vec<ZoneSummary>> {
+        let query = format!(
+            "SELECT kind, zone, SUM(seconds) FROM activity_zones JOIN activities ON activities.id = activity_zones.activity_id WHERE {} GROUP BY kind, zone ORDER BY kind, zone",
+            condition.sql
+        );
+
+        let mut stmt = db.connection().prepare(&query)?;
+        let rows = stmt
+            .query_map(params_from_iter(&condition.values), |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, f64>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let zones = ZoneKind::ALL
+            .into_iter()
+            .filter_map(|kind| {
+                let times: Vec<(i64, f64)> = rows
+                    .iter()
+                    .filter(|(name, _, _)| ZoneKind::from_name(name) == Some(kind))
+                    .map(|(_, zone, seconds)| (*zone, *seconds))
+                    .collect();
+                let total: f64 = times.iter().map(|(_, seconds)| seconds).sum();
+                if total <= 0.0 {
+                    return None;
+                }
+
+                Some(ZoneSummary {
+                    kind,
+                    total,
+                    zones: times
+                        .into_iter()
+                        .map(|(zone, seconds)| ZoneTime {
+                            zone,
+                            seconds,
+                            percent: seconds / total * 100.0,
+                        })
+                        .collect(),
+                })
+            })
+            .collect();
+
+        Ok(zones)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::types::Value;
+
+    #[test]
+    fn averages_without_values_are_none() {
+        let mut accumulator = Accumulator {
+            count: 2,
+            ..Accumulator::default()
+        };
+        accumulator.add(Metric::Distance, Some(5000.0), 2);
+        accumulator.add(Metric::Calories, None, 0);
+
+        let totals = accumulator.totals(&Metric::ALL);
+        assert_eq!(totals.get(Metric::Duration), Some(0.0));
+        assert_eq!(totals.get(Metric::Distance), Some(5000.0));
+        assert_eq!(totals.get(Metric::Calories), None);
+
+        accumulator.add(Metric::Calories, Some(900.0), 2);
+        assert_eq!(
+            accumulator.totals(&Metric::ALL).get(Metric::Calories),
+            Some(450.0)
+        );
+    }
+
+    #[test]
+    fn deltas_need_both_values() {
+        let delta = MetricDelta::new(Metric::Calories, Some(260.0), Some(200.0));
+        assert_eq!(delta.change, Some(60.0));
+        assert_eq!(delta.percent, Some(30.0));
+
+        let delta = MetricDelta::new(Metric::Calories, Some(7.0), None);
+        assert_eq!(delta.change, None);
+        assert_eq!(delta.percent, None);
+
+        let delta = MetricDelta::new(Metric::Distance, Some(460.0), Some(0.0));
+        assert_eq!(delta.change, Some(460.0));
+        assert_eq!(delta.percent, None);
+    }
+
+    #[test]
+    fn sports_and_sub_sports_are_bound() {
+        let sports = ["run".to_string()];
+        let sub_sports = ["trail".to_string(), "x') OR 1=1 --".to_string()];
+        let request = SummaryRequest {
+            period: Period::Year(2026),
+            sports: Some(&sports),
+            sub_sports: Some(&sub_sports),
+            metrics: &Metric::ALL,
+            list_activities: false,
+            zones: false,
+            compare: None,
+        };
+        let condition = request.condition();
+        assert!(!condition.sql.contains('\''));
+        assert!(condition.sql.contains("sub_sport COLLATE NOCASE IN (?, ?)"));
+        let texts: Vec<&Value> = condition.values[2..].iter().collect();
+        assert_eq!(
+            texts,
+            [
+                &Value::Text("running".to_string()),
+                &Value::Text("trail".to_string()),
+                &Value::Text("x') OR 1=1 --".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use rusqlite::types::Value;
+use rusqlite::{Connection, params_from_iter};
+use serde::Serialize;
+use std::collections::HashMap;
+
+// stroke of lengths that were recorded without one
+const UNKNOWN_STROKE: &str = "unknown";
+
+// one stored length with the pool length of its swim
+#[derive(Debug, Clone)]
+pub struct SwimLength {
+    // in seconds
+    pub duration: f64,
+    pub strokes: Option<i64>,
+    pub stroke: Option<String>,
+    pub active: bool,
+}
+
+// one swimming activity with its lengths, empty for open water
+#[derive(Debug, Clone)]
+pub struct Swim {
+    // in m
+    pub pool_length: Option<f64>,
+    // in m
+    pub distance: Option<f64>,
+    // in seconds
+    pub duration: f64,
+    pub lengths: Vec<SwimLength>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StrokeDistance {
+    pub stroke: String,
+    pub lengths: i64,
+    // in m
+    pub distance: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SwimSummary {
+    pub swims: i64,
+    // swims with lengths, only these have strokes, SWOLF and rest intervals
+    pub pool_swims: i64,
+    // in m
+    pub distance: f64,
+    // in seconds, without rest intervals
+    pub swim_time: f64,
+    // seconds per 100 m
+    pub pace: Option<f64>,
+    // longest distance first
+    pub strokes: Vec<StrokeDistance>,
+    // seconds plus strokes per active length
+    pub avg_swolf: Option<f64>,
+    pub rest_intervals: i64,
+    // in seconds
+    pub rest_time: f64,
+}
+
+impl SwimSummary {
+    pub fn compute(swims: &[Swim]) -> Option<Self> {
+        if swims.is_empty() {
+            return None;
+        }
+
+        let mut distance = 0.0;
+        let mut swim_time = 0.0;
+        let mut strokes: HashMap<String, StrokeDistance> = HashMap::new();
+        let mut swolf = (0.0, 0);
+        let mut rest_intervals = 0;
+        let mut rest_time = 0.0;
+        let mut pool_swims = 0;
+
+        for swim in swims {
+            let Some(pool_length) = swim.pool_length.filter(|_| !swim.lengths.is_empty()) else {
+                // open water and swims recorded without lengths only have session totals
+                distance += swim.distance.unwrap_or(0.0);
+                swim_time += swim.duration;
+                continue;
+            };
+            pool_swims += 1;
+
+            for length in &swim.lengths {
+                if !length.active {
+                    rest_intervals += 1;
+                    rest_time += length.duration;
+                    continue;
+                }
+
+                distance += pool_length;
+                swim_time += length.duration;
+                let entry = Self::stroke_entry(
+                    &mut strokes,
+                    length.stroke.as_deref().unwrap_or(UNKNOWN_STROKE),
+                );
+                entry.lengths += 1;
+                entry.distance += pool_length;
+                if let Some(count) = length.strokes.filter(|count| *count > 0) {
+                    swolf.0 += length.duration + count as f64;
+                    swolf.1 += 1;
+                }
+            }
+        }
+
+        let mut strokes: Vec<StrokeDistance> = strokes
+            .into_values()
+            .filter(|stroke| stroke.distance > 0.0)
+            .collect();
+        strokes.sort_by(|a, b| {
+            b.distance
+                .total_cmp(&a.distance)
+                .then_with(|| a.stroke.cmp(&b.stroke))
+        });
+
+        Some(SwimSummary {
+            swims: swims.len() as i64,
+            pool_swims,
+            distance,
+            swim_time,
+            pace: (distance > 0.0).then(|| swim_time / distance * 100.0),
+            strokes,
+            avg_swolf: (swolf.1 > 0).then(|| swolf.0 / swolf.1 as f64),
+            rest_intervals,
+            rest_time,
+        })
+    }
+
+    fn stroke_entry<'a>(
+        strokes: &'a mut HashMap<String, StrokeDistance>,
+        stroke: &str,
+    ) -> &'a mut StrokeDistance {
+        strokes
+            .entry(stroke.to_string())
+            .or_insert_with(|| StrokeDistance {
+                stroke: stroke.to_string(),
+                lengths: 0,
+                distance: 0.0,
+            })
+    }
+}
+
+impl Swim {
+    // swimming activities matching the condition, with their lengths
+    pub fn load(conn: &Connection, condition: &str, values: &[Value]) -> anyhow::Result<Vec<Swim>> {
+        let query = format!(
+            "SELECT id, pool_length, distance, duration FROM activities WHERE sport = 'swimming' AND {}",
+            condition
+        );
+        let mut stmt = conn.prepare(&query)?;
+        let swims = stmt
+            .query_map(params_from_iter(values), |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    Swim {
+                        pool_length: row.get(1)?,
+                        distance: row.get(2)?,
+                        duration: row.get::<_, Option<f64>>(3)?.unwrap_or(0.0),
+                        lengths: Vec::new(),
+                    },
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut lengths_stmt = conn.prepare(
+            "SELECT duration, strokes, stroke, active FROM lengths WHERE activity_id = ?1 ORDER BY length",
+        )?;
+        let mut result = Vec::with_capacity(swims.len());
+        for (id, mut swim) in swims {
+            swim.lengths = lengths_stmt
+                .query_map([id], |row| {
+                    Ok(SwimLength {
+                        duration: row.get::<_, Option<f64>>(0)?.unwrap_or(0.0),
+                        strokes: row.get(1)?,
+                        stroke: row.get(2)?,
+                        active: row.get(3)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            result.push(swim);
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn length(duration: f64, strokes: i64, stroke: &str, active: bool) -> SwimLength {
+        SwimLength {
+            duration,
+            strokes: Some(strokes),
+            stroke: Some(stroke.to_string()),
+            active,
+        }
+    }
+
+    #[test]
+    fn pool_swim() {
+        let swim = Swim {
+            pool_length: Some(25.0),
+            distance: Some(75.0),
+            duration: 120.0,
+            lengths: vec![
+                length(30.0, 15, "freestyle", true),
+                length(20.0, 0, "", false),
+                length(35.0, 17, "freestyle", true),
+                length(40.0, 13, "breaststroke", true),
+            ],
+        };
+        let summary = SwimSummary::compute(&[swim]).unwrap();
+        assert_eq!(summary.pool_swims, 1);
+        assert_eq!(summary.distance, 75.0);
+        assert_eq!(summary.swim_time, 105.0);
+        assert_eq!(summary.pace, Some(140.0));
+        assert_eq!(summary.avg_swolf, Some(50.0));
+        assert_eq!(summary.rest_intervals, 1);
+        assert_eq!(summary.rest_time, 20.0);
+        let strokes: Vec<(&str, f64)> = summary
+            .strokes
+            .iter()
+            .map(|stroke| (stroke.stroke.as_str(), stroke.distance))
+            .collect();
+        assert_eq!(strokes, [("freestyle", 50.0), ("breaststroke", 25.0)]);
+    }
+
+    #[test]
+    fn open_water_swim_has_only_totals() {
+        let swim = Swim {
+            pool_length: None,
+            distance: Some(1500.0),
+            duration: 1800.0,
+            lengths: Vec::new(),
+        };
+        let summary = SwimSummary::compute(&[swim]).unwrap();
+        assert_eq!(summary.pool_swims, 0);
+        assert_eq!(summary.distance, 1500.0);
+        assert_eq!(summary.pace, Some(120.0));
+        assert!(summary.strokes.is_empty());
+        assert_eq!(summary.avg_swolf, None);
+        assert_eq!(summary.rest_intervals, 0);
+    }
+}
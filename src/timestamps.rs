@@ -0,0 +1,42 @@
+use chrono::{DateTime, FixedOffset, Local, Offset, TimeZone};
+use rusqlite::Row;
+
+// timestamps are stored as UTC epoch seconds; activities also store the UTC offset (in seconds)
+// of where they were recorded, so they can be shown and grouped by their own local time
+
+// offset of this machine's timezone at `timestamp`, for activities without a recorded offset
+pub fn machine_offset(timestamp: &DateTime<Local>) -> i32 {
+    timestamp.offset().fix().local_minus_utc()
+}
+
+// activity time in the timezone it was recorded in
+pub fn activity_time(
+    row: &Row,
+    timestamp_index: usize,
+    offset_index: usize,
+) -> rusqlite::Result<DateTime<FixedOffset>> {
+    let timestamp: i64 = row.get(timestamp_index)?;
+    let offset: i32 = row.get(offset_index)?;
+    let offset = FixedOffset::east_opt(offset).ok_or(rusqlite::Error::IntegralValueOutOfRange(
+        offset_index,
+        offset.into(),
+    ))?;
+
+    offset
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .ok_or(rusqlite::Error::IntegralValueOutOfRange(
+            timestamp_index,
+            timestamp,
+        ))
+}
+
+// time in this machine's timezone
+pub fn local_time(row: &Row, index: usize) -> rusqlite::Result<DateTime<Local>> {
+    let timestamp: i64 = row.get(index)?;
+
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .ok_or(rusqlite::Error::IntegralValueOutOfRange(index, timestamp))
+}
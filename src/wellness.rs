@@ -0,0 +1,524 @@
+use crate::timestamps;
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use fitparser::profile::MesgNum;
+use fitparser::{FitDataField, Value};
+use rusqlite::types::Type;
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+// FIT timestamps count seconds from 1989-12-31 00:00 UTC
+const FIT_EPOCH: i64 = 631_065_600;
+
+// sleep level samples further apart than this belong to different nights
+const MAX_SLEEP_GAP: i64 = 3 * 3600;
+
+// wellness samples of one .fit file, timestamps in UTC epoch seconds
+#[derive(Debug, Default)]
+pub struct WellnessSamples {
+    // last full timestamp, the 16 bit timestamps of monitoring messages count from it
+    last_timestamp: Option<i64>,
+    // cumulative steps of the day per activity type
+    steps: Vec<(i64, String, f64)>,
+    resting_hr: Vec<(i64, f64)>,
+    // start of each sleep level (awake, light, deep, rem)
+    sleep_levels: Vec<(i64, String)>,
+    stress: Vec<(i64, f64)>,
+    body_battery: Vec<(i64, f64)>,
+    hrv: Vec<HrvSample>,
+}
+
+#[derive(Debug)]
+struct HrvSample {
+    timestamp: i64,
+    // in ms
+    last_night: Option<f64>,
+    weekly: Option<f64>,
+    // balanced, unbalanced, low, poor
+    status: Option<String>,
+}
+
+// wellness of one local day from one .fit file
+#[derive(Debug, Clone, Default)]
+pub struct WellnessDay {
+    pub date: NaiveDate,
+    pub steps: Option<i64>,
+    // in bpm
+    pub resting_hr: Option<f64>,
+    // in seconds, for the night ending on this day
+    pub sleep_deep: Option<f64>,
+    pub sleep_light: Option<f64>,
+    pub sleep_rem: Option<f64>,
+    pub sleep_awake: Option<f64>,
+    // sum and number of stress samples (0-100)
+    pub stress_total: Option<f64>,
+    pub stress_samples: Option<i64>,
+    pub body_battery_min: Option<f64>,
+    pub body_battery_max: Option<f64>,
+    // in ms
+    pub hrv_last_night: Option<f64>,
+    pub hrv_weekly: Option<f64>,
+    pub hrv_status: Option<String>,
+    // of the HRV summary the values are from
+    pub hrv_timestamp: Option<i64>,
+}
+
+// stored wellness of one day, combined over all files that cover it
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyWellness {
+    pub date: NaiveDate,
+    pub steps: Option<i64>,
+    // in bpm
+    pub resting_hr: Option<f64>,
+    // in seconds, light, deep and REM sleep
+    pub sleep: Option<f64>,
+    pub sleep_deep: Option<f64>,
+    pub sleep_rem: Option<f64>,
+    // in seconds, awake during the night
+    pub sleep_awake: Option<f64>,
+    pub avg_stress: Option<f64>,
+    pub body_battery_min: Option<f64>,
+    pub body_battery_max: Option<f64>,
+    // in ms
+    pub hrv: Option<f64>,
+    pub hrv_status: Option<String>,
+}
+
+impl WellnessSamples {
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+            && self.resting_hr.is_empty()
+            && self.sleep_levels.is_empty()
+            && self.stress.is_empty()
+            && self.body_battery.is_empty()
+            && self.hrv.is_empty()
+    }
+
+    pub fn add(&mut self, kind: MesgNum, fields: &[FitDataField]) {
+        let field = |name: &str| fields.iter().find(|field| field.name() == name);
+        let number = |name: &str| -> Option<f64> {
+            field(name).and_then(|field| field.clone().into_value().try_into().ok())
+        };
+        let text = |name: &str| field(name).map(|field| field.value().to_string());
+
+        if let Some(timestamp) = field("timestamp").and_then(|field| Self::epoch(field.value())) {
+            self.last_timestamp = Some(timestamp);
+        }
+        let timestamp = match number("timestamp_16") {
+            Some(timestamp_16) if field("timestamp").is_none() => self.last_timestamp.map(|last| {
+                let last_fit = last - FIT_EPOCH;
+                last + ((timestamp_16 as i64 - last_fit) & 0xFFFF)
+            }),
+            _ => self.last_timestamp,
+        };
+
+        match kind {
+            MesgNum::Monitoring => {
+                let Some(timestamp) = timestamp else {
+                    return;
+                };
+                let activity_type = text("activity_type").unwrap_or_default();
+                // cycles are half steps when the step subfield could not be resolved
+                let steps = number("steps").or_else(|| match activity_type.as_str() {
+                    "walking" | "running" => number("cycles").map(|cycles| cycles * 2.0),
+                    _ => None,
+                });
+                if let Some(steps) = steps {
+                    self.steps.push((timestamp, activity_type, steps));
+                }
+            }
+            MesgNum::MonitoringHrData => {
+                let resting_hr = number("current_day_resting_heart_rate")
+                    .or_else(|| number("resting_heart_rate"))
+                    .filter(|hr| *hr > 0.0);
+                if let (Some(timestamp), Some(resting_hr)) = (timestamp, resting_hr) {
+                    self.resting_hr.push((timestamp, resting_hr));
+                }
+            }
+            MesgNum::SleepLevel => {
+                if let (Some(timestamp), Some(level)) = (timestamp, text("sleep_level")) {
+                    self.sleep_levels.push((timestamp, level));
+                }
+            }
+            MesgNum::StressLevel => {
+                let time = field("stress_level_time").and_then(|field| Self::epoch(field.value()));
+                let stress = number("stress_level_value").filter(|s| (0.0..=100.0).contains(s));
+                if let (Some(time), Some(stress)) = (time.or(timestamp), stress) {
+                    self.stress.push((time, stress));
+                }
+            }
+            MesgNum::HsaBodyBatteryData => {
+                let (Some(timestamp), Some(levels)) = (timestamp, field("level")) else {
+                    return;
+                };
+                let interval = number("processing_interval").unwrap_or(60.0) as i64;
+                for (index, level) in Self::numbers(levels.value()).into_iter().enumerate() {
+                    // negative levels mark samples without a reading
+                    if (0.0..=100.0).contains(&level) {
+                        self.body_battery
+                            .push((timestamp + index as i64 * interval, level));
+                    }
+                }
+            }
+            MesgNum::HrvStatusSummary => {
+                if let Some(timestamp) = timestamp {
+                    self.hrv.push(HrvSample {
+                        timestamp,
+                        last_night: number("last_night_average"),
+                        weekly: number("weekly_average"),
+                        status: text("status"),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // samples grouped by the local day they were recorded on
+    pub fn days(&self, utc_offset: Option<i32>) -> Vec<WellnessDay> {
+        let date = |time: i64| {
+            let offset = utc_offset.unwrap_or_else(|| {
+                Local
+                    .timestamp_opt(time, 0)
+                    .single()
+                    .map_or(0, |local| timestamps::machine_offset(&local))
+            });
+            DateTime::from_timestamp(time + offset as i64, 0)
+                .unwrap_or_default()
+                .date_naive()
+        };
+
+        let mut days: BTreeMap<NaiveDate, WellnessDay> = BTreeMap::new();
+
+        // counters restart every day, so a day has the sum of its highest count per type;
+        // monitoring timestamps end the interval they count, so midnight belongs to the day before
+        let mut steps: HashMap<(NaiveDate, &str), f64> = HashMap::new();
+        for (time, activity_type, count) in &self.steps {
+            let entry = steps.entry((date(time - 1), activity_type)).or_default();
+            *entry = entry.max(*count);
+        }
+        for ((date, _), count) in steps {
+            let entry = Self::day(&mut days, date);
+            entry.steps = Some(entry.steps.unwrap_or(0) + count as i64);
+        }
+
+        // the resting heart rate of a day is only revised downwards
+        for (time, resting_hr) in &self.resting_hr {
+            let entry = Self::day(&mut days, date(*time));
+            entry.resting_hr = Some(
+                entry
+                    .resting_hr
+                    .map_or(*resting_hr, |hr| hr.min(*resting_hr)),
+            );
+        }
+
+        // each level lasts until the next one; a night counts for the day it ends on
+        for night in self
+            .sleep_levels
+            .chunk_by(|a, b| b.0 - a.0 <= MAX_SLEEP_GAP)
+        {
+            let Some(last) = night.last() else {
+                continue;
+            };
+            let entry = Self::day(&mut days, date(last.0));
+            for pair in night.windows(2) {
+                let duration = (pair[1].0 - pair[0].0) as f64;
+                let slot = match pair[0].1.as_str() {
+                    "deep" => &mut entry.sleep_deep,
+                    "light" => &mut entry.sleep_light,
+                    "rem" => &mut entry.sleep_rem,
+                    "awake" => &mut entry.sleep_awake,
+                    _ => continue,
+                };
+                *slot = Some(slot.unwrap_or(0.0) + duration);
+            }
+        }
+
+        for (time, stress) in &self.stress {
+            let entry = Self::day(&mut days, date(*time));
+            entry.stress_total = Some(entry.stress_total.unwrap_or(0.0) + stress);
+            entry.stress_samples = Some(entry.stress_samples.unwrap_or(0) + 1);
+        }
+
+        for (time, level) in &self.body_battery {
+            let entry = Self::day(&mut days, date(*time));
+            entry.body_battery_min = Some(entry.body_battery_min.map_or(*level, |l| l.min(*level)));
+            entry.body_battery_max = Some(entry.body_battery_max.map_or(*level, |l| l.max(*level)));
+        }
+
+        // a later summary of the same day revises the earlier ones
+        for sample in &self.hrv {
+            let entry = Self::day(&mut days, date(sample.timestamp));
+            if entry
+                .hrv_timestamp
+                .is_some_and(|time| time > sample.timestamp)
+            {
+                continue;
+            }
+            entry.hrv_last_night = sample.last_night.or(entry.hrv_last_night);
+            entry.hrv_weekly = sample.weekly.or(entry.hrv_weekly);
+            entry.hrv_status = sample.status.clone().or(entry.hrv_status.take());
+            entry.hrv_timestamp = Some(sample.timestamp);
+        }
+
+        days.into_values().collect()
+    }
+
+    fn day(days: &mut BTreeMap<NaiveDate, WellnessDay>, date: NaiveDate) -> &mut WellnessDay {
+        days.entry(date).or_insert_with(|| WellnessDay {
+            date,
+            ..Default::default()
+        })
+    }
+
+    fn epoch(value: &Value) -> Option<i64> {
+        match value {
+            Value::Timestamp(timestamp) => Some(timestamp.timestamp()),
+            _ => None,
+        }
+    }
+
+    fn numbers(value: &Value) -> Vec<f64> {
+        match value {
+            Value::Array(values) => values
+                .iter()
+                .filter_map(|value| value.clone().try_into().ok())
+                .collect(),
+            value => value.clone().try_into().ok().into_iter().collect(),
+        }
+    }
+}
+
+impl WellnessDay {
+    pub fn save_all(conn: &Connection, file_id: i64, days: &[WellnessDay]) -> anyhow::Result<()> {
+        for day in days {
+            conn.execute(
+                "INSERT INTO daily_wellness (file_id, date, steps, resting_hr, sleep_deep, sleep_light, sleep_rem, sleep_awake, stress_total, stress_samples, body_battery_min, body_battery_max, hrv_last_night, hrv_weekly, hrv_status, hrv_timestamp) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+                params![file_id, day.date.to_string(), day.steps, day.resting_hr, day.sleep_deep, day.sleep_light, day.sleep_rem, day.sleep_awake, day.stress_total, day.stress_samples, day.body_battery_min, day.body_battery_max, day.hrv_last_night, day.hrv_weekly, day.hrv_status, day.hrv_timestamp],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl DailyWellness {
+    // days from `from` (inclusive) to `to` (exclusive), combining the files of each day
+    pub fn load(conn: &Connection, from: NaiveDate, to: NaiveDate) -> anyhow::Result<Vec<Self>> {
+        // step counters continue over the files of a day, so the highest count is the total;
+        // sleep and monitoring files both record a night, so sleep is taken from the file with
+        // the most sleep, and HRV from the latest summary of the day (rows imported before
+        // summaries were timed have no hrv_timestamp and come last)
+        let mut stmt = conn.prepare(
+            "WITH days AS (
+                SELECT date, MAX(steps) AS steps, MIN(resting_hr) AS resting_hr, SUM(stress_total) / SUM(stress_samples) AS avg_stress, MIN(body_battery_min) AS body_battery_min, MAX(body_battery_max) AS body_battery_max
+                FROM daily_wellness WHERE date >= ?1 AND date < ?2 GROUP BY date
+            ), sleep AS (
+                SELECT date, sleep_deep, sleep_light, sleep_rem, sleep_awake,
+                ROW_NUMBER() OVER (PARTITION BY date ORDER BY COALESCE(sleep_deep, 0) + COALESCE(sleep_light, 0) + COALESCE(sleep_rem, 0) DESC) AS position
+                FROM daily_wellness WHERE date >= ?1 AND date < ?2 AND COALESCE(sleep_deep, sleep_light, sleep_rem, sleep_awake) IS NOT NULL
+            ), hrv AS (
+                SELECT date, hrv_last_night, hrv_status,
+                ROW_NUMBER() OVER (PARTITION BY date ORDER BY hrv_timestamp DESC NULLS LAST) AS position
+                FROM daily_wellness WHERE date >= ?1 AND date < ?2 AND COALESCE(hrv_last_night, hrv_status) IS NOT NULL
+            )
+            SELECT days.date, steps, resting_hr, sleep_deep, sleep_light, sleep_rem, avg_stress, body_battery_min, body_battery_max, hrv_last_night, hrv_status, sleep_awake
+            FROM days
+            LEFT JOIN sleep ON sleep.date = days.date AND sleep.position = 1
+            LEFT JOIN hrv ON hrv.date = days.date AND hrv.position = 1
+            ORDER BY days.date",
+        )?;
+        let days = stmt
+            .query_map(params![from.to_string(), to.to_string()], |row| {
+                let deep: Option<f64> = row.get(3)?;
+                let light: Option<f64> = row.get(4)?;
+                let rem: Option<f64> = row.get(5)?;
+                let sleep = match (deep, light, rem) {
+                    (None, None, None) => None,
+                    _ => Some(deep.unwrap_or(0.0) + light.unwrap_or(0.0) + rem.unwrap_or(0.0)),
+                };
+                let date: String = row.get(0)?;
+                let date = date.parse::<NaiveDate>().map_err(|err| {
+                    rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(err))
+                })?;
+                Ok(DailyWellness {
+                    date,
+                    steps: row.get(1)?,
+                    resting_hr: row.get(2)?,
+                    sleep,
+                    sleep_deep: deep,
+                    sleep_rem: rem,
+                    sleep_awake: row.get(11)?,
+                    avg_stress: row.get(6)?,
+                    body_battery_min: row.get(7)?,
+                    body_battery_max: row.get(8)?,
+                    hrv: row.get(9)?,
+                    hrv_status: row.get(10)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(days)
+    }
+}
+
+// days of the most recent stretch that is compared against the rest of the period
+pub const RECENT_DAYS: i64 = 7;
+
+// a resting heart rate this far above its baseline is a common sign of fatigue or illness
+pub const RESTING_HR_ALERT: f64 = 5.0;
+
+type TrendValue = fn(&DailyWellness) -> Option<f64>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WellnessTrend {
+    pub metric: String,
+    // average over the whole period
+    pub average: Option<f64>,
+    // average over the last RECENT_DAYS days
+    pub recent: Option<f64>,
+    // average over the days before them
+    pub baseline: Option<f64>,
+}
+
+impl WellnessTrend {
+    pub fn change(&self) -> Option<f64> {
+        Some(self.recent? - self.baseline?)
+    }
+
+    // steps, resting heart rate, sleep, HRV and stress, with `until` as the end of the period
+    pub fn compute(days: &[DailyWellness], until: NaiveDate) -> Vec<WellnessTrend> {
+        let recent_start = until - chrono::Duration::days(RECENT_DAYS);
+        let metrics: [(&str, TrendValue); 5] = [
+            ("steps", |day| day.steps.map(|steps| steps as f64)),
+            ("resting_hr", |day| day.resting_hr),
+            ("sleep", |day| day.sleep),
+            ("hrv", |day| day.hrv),
+            ("stress", |day| day.avg_stress),
+        ];
+
+        metrics
+            .into_iter()
+            .map(|(metric, value)| {
+                let average = |filter: &dyn Fn(&DailyWellness) -> bool| {
+                    let values: Vec<f64> = days
+                        .iter()
+                        .filter(|day| filter(day))
+                        .filter_map(value)
+                        .collect();
+                    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+                };
+                WellnessTrend {
+                    metric: metric.to_string(),
+                    average: average(&|_| true),
+                    recent: average(&|day| day.date >= recent_start),
+                    baseline: average(&|day| day.date < recent_start),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hrv(timestamp: i64, last_night: f64, status: &str) -> HrvSample {
+        HrvSample {
+            timestamp,
+            last_night: Some(last_night),
+            weekly: None,
+            status: Some(status.to_string()),
+        }
+    }
+
+    #[test]
+    fn latest_hrv_summary_of_a_day() {
+        let samples = WellnessSamples {
+            hrv: vec![hrv(7200, 52.0, "unbalanced"), hrv(3600, 40.0, "balanced")],
+            ..WellnessSamples::default()
+        };
+        let days = samples.days(Some(0));
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0].hrv_last_night, Some(52.0));
+        assert_eq!(days[0].hrv_status.as_deref(), Some("unbalanced"));
+        assert_eq!(days[0].hrv_timestamp, Some(7200));
+    }
+
+    #[test]
+    fn load_combines_the_files_of_a_day() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE daily_wellness (id INTEGER PRIMARY KEY, file_id INTEGER NOT NULL, date TEXT NOT NULL, steps INTEGER, resting_hr REAL, sleep_deep REAL, sleep_light REAL, sleep_rem REAL, sleep_awake REAL, stress_total REAL, stress_samples INTEGER, body_battery_min REAL, body_battery_max REAL, hrv_last_night REAL, hrv_weekly REAL, hrv_status TEXT, hrv_timestamp INTEGER);",
+        )
+        .unwrap();
+        let date = NaiveDate::from_ymd_opt(2026, 5, 14).unwrap();
+        let night = WellnessDay {
+            date,
+            sleep_deep: Some(3600.0),
+            sleep_light: Some(14400.0),
+            sleep_rem: Some(5400.0),
+            sleep_awake: Some(1200.0),
+            ..WellnessDay::default()
+        };
+        // a sleep file, and a monitoring file with the same night, steps and a later HRV status
+        WellnessDay::save_all(
+            &conn,
+            1,
+            &[WellnessDay {
+                steps: Some(4000),
+                hrv_last_night: Some(40.0),
+                hrv_status: Some("unbalanced".to_string()),
+                hrv_timestamp: Some(1000),
+                ..night.clone()
+            }],
+        )
+        .unwrap();
+        WellnessDay::save_all(
+            &conn,
+            2,
+            &[WellnessDay {
+                steps: Some(9000),
+                hrv_last_night: Some(55.0),
+                hrv_status: Some("balanced".to_string()),
+                hrv_timestamp: Some(2000),
+                ..night
+            }],
+        )
+        .unwrap();
+
+        let days = DailyWellness::load(&conn, date, date.succ_opt().unwrap()).unwrap();
+        assert_eq!(days.len(), 1);
+        let day = &days[0];
+        assert_eq!(day.steps, Some(9000));
+        assert_eq!(day.sleep, Some(23400.0));
+        assert_eq!(day.sleep_deep, Some(3600.0));
+        assert_eq!(day.sleep_awake, Some(1200.0));
+        assert_eq!(day.hrv, Some(55.0));
+        assert_eq!(day.hrv_status.as_deref(), Some("balanced"));
+    }
+
+    #[test]
+    fn load_keeps_hrv_imported_without_a_timestamp() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE daily_wellness (id INTEGER PRIMARY KEY, file_id INTEGER NOT NULL, date TEXT NOT NULL, steps INTEGER, resting_hr REAL, sleep_deep REAL, sleep_light REAL, sleep_rem REAL, sleep_awake REAL, stress_total REAL, stress_samples INTEGER, body_battery_min REAL, body_battery_max REAL, hrv_last_night REAL, hrv_weekly REAL, hrv_status TEXT, hrv_timestamp INTEGER);",
+        )
+        .unwrap();
+        let date = NaiveDate::from_ymd_opt(2026, 5, 14).unwrap();
+        WellnessDay::save_all(
+            &conn,
+            1,
+            &[WellnessDay {
+                date,
+                hrv_last_night: Some(48.0),
+                hrv_status: Some("balanced".to_string()),
+                ..WellnessDay::default()
+            }],
+        )
+        .unwrap();
+
+        let days = DailyWellness::load(&conn, date, date.succ_opt().unwrap()).unwrap();
+        assert_eq!(days[0].hrv, Some(48.0));
+        assert_eq!(days[0].hrv_status.as_deref(), Some("balanced"));
+    }
+}
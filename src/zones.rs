@@ -0,0 +1,249 @@
+use crate::athlete::Athlete;
+use crate::streams::{MAX_SAMPLE_GAP, Stream};
+use rusqlite::{Connection, params};
+use serde::Serialize;
+
+// default zone boundaries as fractions of the athlete's thresholds
+const MAX_HR_ZONES: [f64; 4] = [0.6, 0.7, 0.8, 0.9];
+const LTHR_ZONES: [f64; 4] = [0.81, 0.9, 0.94, 1.0];
+const FTP_ZONES: [f64; 6] = [0.55, 0.75, 0.9, 1.05, 1.2, 1.5];
+// fractions of threshold speed
+const THRESHOLD_PACE_ZONES: [f64; 4] = [0.78, 0.88, 0.95, 1.02];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ZoneKind {
+    Hr,
+    Power,
+    Pace,
+}
+
+impl ZoneKind {
+    pub const ALL: [ZoneKind; 3] = [ZoneKind::Hr, ZoneKind::Power, ZoneKind::Pace];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ZoneKind::Hr => "hr",
+            ZoneKind::Power => "power",
+            ZoneKind::Pace => "pace",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ZoneKind::Hr => "Heart Rate",
+            ZoneKind::Power => "Power",
+            ZoneKind::Pace => "Pace",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    // zone boundaries from the profile, or derived from its thresholds; pace boundaries
+    // are seconds per km from slowest to fastest, all others ascending
+    pub fn boundaries(&self, athlete: &Athlete) -> Option<Vec<f64>> {
+        match self {
+            ZoneKind::Hr => athlete
+                .hr_zones
+                .clone()
+                .or_else(|| {
+                    athlete
+                        .lthr
+                        .map(|lthr| LTHR_ZONES.map(|f| f * lthr).to_vec())
+                })
+                .or_else(|| {
+                    athlete
+                        .max_hr
+                        .map(|max_hr| MAX_HR_ZONES.map(|f| f * max_hr).to_vec())
+                }),
+            ZoneKind::Power => athlete
+                .power_zones
+                .clone()
+                .or_else(|| athlete.ftp.map(|ftp| FTP_ZONES.map(|f| f * ftp).to_vec())),
+            ZoneKind::Pace => athlete.pace_zones.clone().or_else(|| {
+                athlete
+                    .threshold_pace
+                    .map(|pace| THRESHOLD_PACE_ZONES.map(|f| pace / f).to_vec())
+            }),
+        }
+    }
+
+    // only running has a threshold pace to compare against
+    fn applies_to(&self, sport: &str) -> bool {
+        match self {
+            ZoneKind::Pace => sport == "running",
+            _ => true,
+        }
+    }
+
+    // 0-based zone of a sample, None if the sample has no value for this kind
+    fn zone(&self, value: f32, boundaries: &[f64]) -> Option<usize> {
+        if value.is_nan() {
+            return None;
+        }
+        let value = value as f64;
+        match self {
+            ZoneKind::Pace => {
+                if value <= 0.0 {
+                    return None;
+                }
+                let pace = 1000.0 / value;
+                Some(boundaries.iter().filter(|b| pace <= **b).count())
+            }
+            _ => Some(boundaries.iter().filter(|b| value >= **b).count()),
+        }
+    }
+}
+
+// seconds spent in each zone of one kind
+#[derive(Debug, Clone)]
+pub struct TimeInZones {
+    pub kind: ZoneKind,
+    pub seconds: Vec<f64>,
+}
+
+impl TimeInZones {
+    // time in zones of every kind the stream and the athlete profile have data for
+    pub fn compute(stream: &Stream, sport: &str, athlete: &Athlete) -> Vec<TimeInZones> {
+        // each sample lasts until the next one, pauses excluded
+        let durations: Vec<f32> = stream
+            .time
+            .iter()
+            .enumerate()
+            .map(|(i, t)| match stream.time.get(i + 1) {
+                Some(next) if next - t <= MAX_SAMPLE_GAP => next - t,
+                Some(_) => 0.0,
+                None => 1.0,
+            })
+            .collect();
+
+        ZoneKind::ALL
+            .into_iter()
+            .filter(|kind| kind.applies_to(sport))
+            .filter_map(|kind| {
+                let boundaries = kind.boundaries(athlete)?;
+                let values = match kind {
+                    ZoneKind::Hr => stream.heart_rate.as_ref(),
+                    ZoneKind::Power => stream.power.as_ref(),
+                    ZoneKind::Pace => stream.speed.as_ref(),
+                }?;
+
+                let mut seconds = vec![0.0; boundaries.len() + 1];
+                for (value, duration) in values.iter().zip(&durations) {
+                    if let Some(zone) = kind.zone(*value, &boundaries) {
+                        seconds[zone] += *duration as f64;
+                    }
+                }
+                Some(TimeInZones { kind, seconds })
+            })
+            .collect()
+    }
+
+    // replace the stored time in zones of an activity
+    pub fn save_all(
+        conn: &Connection,
+        activity_id: i64,
+        zones: &[TimeInZones],
+    ) -> anyhow::Result<()> {
+        conn.execute(
+            "DELETE FROM activity_zones WHERE activity_id = ?1",
+            params![activity_id],
+        )?;
+
+        let mut stmt = conn.prepare(
+            "INSERT INTO activity_zones (activity_id, kind, zone, seconds) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for time_in_zones in zones {
+            for (index, seconds) in time_in_zones.seconds.iter().enumerate() {
+                stmt.execute(params![
+                    activity_id,
+                    time_in_zones.kind.name(),
+                    index as i64 + 1,
+                    seconds
+                ])?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(time: &[f32]) -> Stream {
+        Stream {
+            time: time.to_vec(),
+            ..Stream::default()
+        }
+    }
+
+    #[test]
+    fn time_in_zones_skips_pauses_and_missing_samples() {
+        let athlete = Athlete {
+            hr_zones: Some(vec![120.0, 140.0, 160.0]),
+            ..Athlete::default()
+        };
+        // the 170 bpm sample is followed by a pause, and the last sample counts one second
+        let stream = Stream {
+            heart_rate: Some(vec![110.0, 125.0, 145.0, 165.0, 170.0, f32::NAN, 150.0]),
+            ..stream(&[0.0, 1.0, 3.0, 4.0, 5.0, 30.0, 31.0])
+        };
+
+        let zones = TimeInZones::compute(&stream, "running", &athlete);
+        assert_eq!(zones.len(), 1);
+        assert_eq!(zones[0].kind, ZoneKind::Hr);
+        assert_eq!(zones[0].seconds, vec![1.0, 2.0, 2.0, 1.0]);
+    }
+
+    #[test]
+    fn zones_derived_from_thresholds() {
+        let athlete = Athlete {
+            ftp: Some(200.0),
+            max_hr: Some(200.0),
+            threshold_pace: Some(300.0),
+            ..Athlete::default()
+        };
+        assert_eq!(
+            ZoneKind::Hr.boundaries(&athlete),
+            Some(vec![120.0, 140.0, 160.0, 180.0])
+        );
+        // the lactate threshold takes precedence over the maximum heart rate
+        let with_lthr = Athlete {
+            lthr: Some(100.0),
+            ..athlete.clone()
+        };
+        assert_eq!(
+            ZoneKind::Hr.boundaries(&with_lthr),
+            Some(vec![81.0, 90.0, 94.0, 100.0])
+        );
+
+        let stream = Stream {
+            power: Some(vec![100.0, 200.0, 310.0]),
+            // 400, 312.5 and 250 s/km, and standing still
+            speed: Some(vec![2.5, 3.2, 4.0, 0.0]),
+            ..stream(&[0.0, 1.0, 2.0, 3.0])
+        };
+        let zones_of = |sport| {
+            TimeInZones::compute(&stream, sport, &athlete)
+                .into_iter()
+                .map(|zones| (zones.kind, zones.seconds))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            zones_of("running"),
+            vec![
+                (ZoneKind::Power, vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0]),
+                (ZoneKind::Pace, vec![1.0, 0.0, 0.0, 1.0, 1.0]),
+            ]
+        );
+        // pace zones are only kept for running
+        assert_eq!(
+            zones_of("cycling"),
+            vec![(ZoneKind::Power, vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0])]
+        );
+    }
+}